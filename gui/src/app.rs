@@ -1,7 +1,19 @@
 use linear_regression::linear_regression::LinearRegression;
+use linear_regression::dataset::{Column, Dataset, DatasetMatch};
+use linear_regression::error::{LinearRegressionError, Result};
+use linear_regression::observer::{TrainingControl, TrainingStep};
+use linear_regression::registry::{ModelEntry, Registry};
+use linear_regression::utils::{check_dataset_fingerprint, get_registry_model, get_registry_path, get_validation_rules};
+use linear_regression::validation::ValidationReport;
 use crate::settings::{GridSettings, PlotSettings, SidebarSettings, SidebarTab};
 use crate::components::{Navbar, Sidebar, Plot, ModelErrorScreen};
 
+/// Iterations run by the "Train Model" button.
+const TRAINING_ITERATIONS: usize = 1000;
+
+/// Iterations run per frame, so the regression line animates while training.
+const TRAINING_STEPS_PER_FRAME: usize = 10;
+
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
@@ -13,9 +25,19 @@ pub struct App {
   pub grid_settings: GridSettings,
   pub plot_settings: PlotSettings,
   #[serde(skip)]
-  pub error_message: Option<String>,
+  pub model_error: Option<LinearRegressionError>,
+  #[serde(skip)]
+  pub dataset: Option<Dataset>, // Samples the model was loaded with, with their columns and source
+  #[serde(skip)]
+  pub validation: Option<ValidationReport>, // Rows the validation pass flagged or dropped
   #[serde(skip)]
   pub regression_model: Option<LinearRegression>, // LinearRegression instance
+  #[serde(skip)]
+  pub registered: Option<ModelEntry>, // Registry entry the model was loaded from, if any
+  #[serde(skip)]
+  pub dataset_warning: Option<String>, // Set when the model was trained on other data than the dataset
+  #[serde(skip)]
+  pub registry_entries: Vec<(ModelEntry, Vec<String>)>, // Registered models and their tags
    #[serde(skip)]
   pub predictions: Vec<(f64, f64)>,               // Predictions for dataset
   #[serde(skip)]
@@ -30,6 +52,12 @@ pub struct App {
   pub rmse: Option<f64>, // Root Mean Squared Error
   #[serde(skip)]
   pub r2: Option<f64>, // R² Score
+  #[serde(skip)]
+  pub training_remaining: usize, // Iterations left in the running animation
+  #[serde(skip)]
+  pub training_iterations: usize, // Steps taken since the model was loaded
+  #[serde(skip)]
+  pub last_training_step: Option<TrainingStep>,
 }
 
 impl App {
@@ -46,8 +74,13 @@ impl App {
       sidebar_settings: SidebarSettings::default(),
       grid_settings: GridSettings::new(),
       plot_settings: PlotSettings::new(),
-      error_message: None,
+      model_error: None,
+      dataset: None,
+      validation: None,
       regression_model: None,
+      registered: None,
+      dataset_warning: None,
+      registry_entries: Vec::new(),
       predictions: Vec::new(),
       regression_line: None,
       swapped_regression_line: None,
@@ -55,6 +88,9 @@ impl App {
       mse: None,
       rmse: None,
       r2: None,
+      training_remaining: 0,
+      training_iterations: 0,
+      last_training_step: None,
     };
 
      // Load previous app state (if any) and override the default values.
@@ -68,59 +104,157 @@ impl App {
       }
     }
 
-    // Initialize the regression model.
-    let (model, error_message) = match LinearRegression::new(None) {
-      Ok(model) => (Some(model), None),
-      Err(err) => {
-        log::error!("Failed to initialize regression model: {}", err);
-        (None, Some(format!("Failed to initialize model: {}", err)))
-      },
-    };
+    app.refresh_registry();
 
-    app.regression_model = model;
-    app.error_message = error_message;
+    // Initialize the regression model, from the registry when REGISTRY_MODEL names one.
+    match get_registry_model() {
+      Ok(Some(reference)) => app.load_registered_model(&reference),
+      Ok(None) => app.replace_model(Dataset::load().and_then(|dataset| load_model(None, dataset))),
+      Err(err) => app.replace_model(Err(err)),
+    }
 
     app
   }
 
   /// Reloads the LinearRegression model and updates the state.
   pub fn reload_model(&mut self) {
-    match LinearRegression::new(Some(0.01)) {
-      Ok(model) => {
+    let model = Dataset::load().and_then(|dataset| load_model(Some(0.01), dataset));
+    self.replace_model(model);
+    self.refresh_registry();
+  }
+
+  /// Replaces the model with a registered one, by tag or id, plotted on the dataset.
+  pub fn load_registered_model(&mut self, reference: &str) {
+    match load_registered_model(reference) {
+      Ok((entry, model)) => {
+        log::info!("Loaded registered model {}", entry.id);
+        let trained = model.0.get_dataset_fingerprint();
+        // Streamed fingerprints skip validation, so re-read the file alike
+        let check = if trained.is_some_and(|trained| trained.streamed) {
+          check_dataset_fingerprint::<f64>(trained).unwrap_or(DatasetMatch::Unknown)
+        } else {
+          DatasetMatch::of(trained, model.1.fingerprint())
+        };
+        self.replace_model(Ok(model));
+        self.registered = Some(entry);
+        self.dataset_warning = check.warning();
+        if let Some(warning) = &self.dataset_warning {
+          log::warn!("{}", warning);
+        }
+        self.update_from_model();
+      }
+      Err(err) => self.replace_model(Err(err)),
+    }
+  }
+
+  /// Re-reads the models and tags of the registry. Leaves the list empty
+  /// when there is no registry, e.g. on the web.
+  pub fn refresh_registry(&mut self) {
+    let entries = get_registry_path().and_then(|path| Registry::open(&path)).and_then(|registry| {
+      registry
+        .entries()?
+        .into_iter()
+        .map(|entry| {
+          let tags = registry.tags_of(&entry.id)?;
+          Ok((entry, tags))
+        })
+        .collect()
+    });
+    self.registry_entries = entries.unwrap_or_else(|err| {
+      log::warn!("Cannot read the model registry: {}", err);
+      Vec::new()
+    });
+  }
+
+  /// Replaces the model with one trained on the embedded sample dataset.
+  pub fn use_sample_dataset(&mut self) {
+    self.replace_model(load_model(Some(0.01), Dataset::sample()));
+  }
+
+  /// Installs a freshly loaded model, or the error that prevented loading it.
+  fn replace_model(&mut self, model: Result<(LinearRegression, Dataset, ValidationReport)>) {
+    self.registered = None;
+    self.dataset_warning = None;
+    self.regression_line = None;
+    self.predictions = Vec::new();
+    self.mae = None;
+    self.mse = None;
+    self.rmse = None;
+    self.r2 = None;
+    self.training_remaining = 0;
+    self.training_iterations = 0;
+    self.last_training_step = None;
+
+    match model {
+      Ok((model, dataset, validation)) => {
+        log::info!("Loaded dataset from {}", dataset.source());
         self.regression_model = Some(model);
-        self.regression_line = None;
-        self.predictions = Vec::new();
-        self.error_message = None;
-        self.mae = None;
-        self.mse = None;
-        self.rmse = None;
-        self.r2 = None;
+        self.dataset = Some(dataset);
+        self.validation = Some(validation);
+        self.model_error = None;
       }
       Err(err) => {
         log::error!("Failed to initialize regression model: {}", err);
         self.regression_model = None;
-        self.regression_line = None;
-        self.predictions = Vec::new();
-        self.mae = None;
-        self.mse = None;
-        self.rmse = None;
-        self.r2 = None;
-        self.error_message = Some(format!("Failed to initialize model: {}", err));
+        self.dataset = None;
+        self.validation = None;
+        self.model_error = Some(err);
       }
     }
   }
 
-  /// Trains the model and updates the GUI state.
+  /// Starts training; the model is trained a few steps per frame.
   pub fn train_model(&mut self) {
-    if let Some(model) = &mut self.regression_model {
-      model.train(1000); // Train for 1000 iterations
-      self.update_from_model();
+    if self.regression_model.is_some() {
+      self.training_remaining = TRAINING_ITERATIONS;
+    }
+  }
+
+  /// Stops a running training animation.
+  pub fn stop_training(&mut self) {
+    self.training_remaining = 0;
+  }
+
+  /// Whether a training animation is running.
+  pub fn is_training(&self) -> bool {
+    self.training_remaining > 0
+  }
+
+  /// Runs the next batch of training steps and updates the GUI state.
+  fn advance_training(&mut self) {
+    let Some(model) = &mut self.regression_model else {
+      self.training_remaining = 0;
+      return;
+    };
+
+    let steps = self.training_remaining.min(TRAINING_STEPS_PER_FRAME);
+    let mut last_step = None;
+    let ran = match model.train_with(steps, &mut |step: &TrainingStep| {
+      last_step = Some(*step);
+      TrainingControl::Continue
+    }) {
+      Ok(ran) => ran,
+      Err(err) => {
+        log::error!("Training failed: {}", err);
+        self.regression_model = None;
+        self.training_remaining = 0;
+        self.model_error = Some(err);
+        return;
+      }
+    };
+
+    // Fewer steps than requested means training converged
+    self.training_remaining = if ran < steps { 0 } else { self.training_remaining - steps };
+    self.training_iterations += ran;
+    if last_step.is_some() {
+      self.last_training_step = last_step;
     }
+    self.update_from_model();
   }
 
 
   /// Computes the least squares regression line (slope and intercept)
-  fn compute_regression_line(data: &Vec<(f64, f64)>) -> Option<(f64, f64)> {
+  fn compute_regression_line(data: &[(f64, f64)]) -> Option<(f64, f64)> {
     let n = data.len() as f64;
     if n < 2.0 {
       return None; // Not enough data points to compute a regression line
@@ -164,18 +298,18 @@ impl App {
       }
 
       // Generate predictions for the dataset
-      self.predictions = dataset.iter().map(|&(x, _)| (x, model.predict(x))).collect();
+      self.predictions = dataset.iter().map(|&(x, _)| (x, model.predict_unchecked(x))).collect();
 
       // Compute Normal Regression Line (y = mx + b)
       self.regression_line = Self::compute_regression_line(&self.predictions);
 
-      let swapped_predictions = dataset.iter().map(|&(_, y)| (y, model.predict(y))).collect();
+      let swapped_predictions: Vec<(f64, f64)> = dataset.iter().map(|&(_, y)| (y, model.predict_unchecked(y))).collect();
       self.swapped_regression_line = Self::compute_regression_line(&swapped_predictions);
-      let (mae, mse, rmse, r2) = model.compute_precision();
-      self.mae = Some(mae);
-      self.mse = Some(mse);
-      self.rmse = Some(rmse);
-      self.r2 = Some(r2);
+      let precision = model.compute_precision().ok();
+      self.mae = precision.map(|(mae, _, _, _)| mae);
+      self.mse = precision.map(|(_, mse, _, _)| mse);
+      self.rmse = precision.map(|(_, _, rmse, _)| rmse);
+      self.r2 = precision.map(|(_, _, _, r2)| r2);
     }
   }
 
@@ -209,6 +343,15 @@ impl App {
     self.regression_model.as_ref().expect("Regression model is not initialized").get_dataset()
   }
 
+  /// Returns the feature and target columns of the loaded dataset.
+  pub fn get_columns(&self) -> (Column, Column) {
+    match &self.dataset {
+      Some(dataset) => (dataset.feature().clone(), dataset.target().clone()),
+      None => (Column::km(), Column::price()),
+    }
+  }
+
+
   /// Returns a reference to the predictions.
   pub fn get_predictions(&self) -> &Vec<(f64, f64)> {
     &self.predictions
@@ -229,6 +372,10 @@ impl eframe::App for App {
   /// Called each time the UI needs repainting, which may be many times per second.
   fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
     self.plot_settings.need_auto_bounds = false;
+    if self.is_training() {
+      self.advance_training();
+      ctx.request_repaint();
+    }
     egui::TopBottomPanel::top("navbar").show(ctx, |ui| {
       ui.horizontal(|ui| {
         ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
@@ -248,7 +395,7 @@ impl eframe::App for App {
 
     
     egui::CentralPanel::default().show(ctx, |ui| {
-      if let Some(_) = self.regression_model {
+      if self.regression_model.is_some() {
         Plot::render(ui, self);
       } else {
         ModelErrorScreen::render(ui, self);
@@ -256,3 +403,24 @@ impl eframe::App for App {
     });
   }
 }
+
+/// Builds a model attached to the rows of `dataset` that pass validation,
+/// ready to be trained.
+fn load_model(learning_rate: Option<f64>, dataset: Dataset) -> Result<(LinearRegression, Dataset, ValidationReport)> {
+  let (dataset, validation) = dataset.clean(&get_validation_rules()?);
+  let mut model = LinearRegression::new(learning_rate);
+  model.set_dataset(&dataset)?;
+  Ok((model, dataset, validation))
+}
+
+/// Loads the registered model `reference` names and attaches the rows of
+/// the dataset that pass validation, keeping its fit.
+fn load_registered_model(reference: &str) -> Result<(ModelEntry, (LinearRegression, Dataset, ValidationReport))> {
+  let registry = Registry::open(&get_registry_path()?)?;
+  let entry = registry.resolve(reference)?;
+  // Models saved in f32 are widened to the f64 the GUI plots with
+  let mut model = registry.load_as::<f64>(&entry.id)?.to_model();
+  let (dataset, validation) = Dataset::load()?.clean(&get_validation_rules()?);
+  model.attach_dataset(&dataset)?;
+  Ok((entry, (model, dataset, validation)))
+}
@@ -1,4 +1,5 @@
 use log::info;
+use linear_regression::error::LinearRegressionError;
 use crate::app::App;
 
 pub struct ModelErrorScreen;
@@ -13,11 +14,13 @@ impl ModelErrorScreen {
       ui.add_space(ui.available_height() * 0.3);
       ui.add_space(20.0);
       ui.label("It seems like the regression model is not properly initialized.");
-      if let Some(error) = &app.error_message {
+      if let Some(error) = &app.model_error {
         ui.add_space(10.0);
-        ui.label(eframe::egui::RichText::new(error)
+        ui.label(eframe::egui::RichText::new(error.to_string())
           .color(eframe::egui::Color32::LIGHT_RED)
           .monospace());
+        ui.add_space(10.0);
+        ui.label(eframe::egui::RichText::new(error.hint()).strong());
       }
       ui.add_space(20.0);
       ui.heading("Here are a few things you can try:");
@@ -31,6 +34,14 @@ impl ModelErrorScreen {
         app.reload_model();
       }
 
+      if matches!(app.model_error, Some(LinearRegressionError::DatasetNotFound { .. })) {
+        ui.add_space(10.0);
+        if ui.button("📦 Use the embedded sample dataset").clicked() {
+          info!("Falling back to the embedded sample dataset...");
+          app.use_sample_dataset();
+        }
+      }
+
       ui.add_space(20.0);
       ui.label("Or just enjoy this cool animation while you troubleshoot:");
       ui.add(eframe::egui::widgets::Spinner::new());
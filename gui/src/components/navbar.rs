@@ -15,7 +15,11 @@ impl Navbar {
         app.reload_model();
       }
 
-      if ui.button("Train Model").clicked() {
+      if app.is_training() {
+        if ui.button("Stop Training").clicked() {
+          app.stop_training();
+        }
+      } else if ui.button("Train Model").clicked() {
         app.train_model();
       }
     });
@@ -22,10 +22,11 @@ impl Plot {
       });
       (scroll, i.pointer.primary_down(), i.modifiers)
     });
+    let (feature, target) = app.get_columns();
     let (x_label, y_label, x_formatter, y_formatter) = if plot_settings.swap_axes {
-      ("Price", "KM", format_price as fn(f64) -> String, format_km as fn(f64) -> String)
+      (target.to_string(), feature.to_string(), format_price as fn(f64) -> String, format_km as fn(f64) -> String)
     } else {
-      ("KM", "Price", format_km as fn(f64) -> String, format_price as fn(f64) -> String)
+      (feature.to_string(), target.to_string(), format_km as fn(f64) -> String, format_price as fn(f64) -> String)
     };
     ui.label("Linear Regression - Gradient Descent");
     egui_plot::Plot::new("Linear Regression Plot")
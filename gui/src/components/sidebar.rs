@@ -21,6 +21,11 @@ impl Sidebar {
 
       ui.separator();
 
+      if !app.registry_entries.is_empty() {
+        Sidebar::render_registry(ui, app);
+        ui.separator();
+      }
+
       ui.group(|ui| {
         ui.set_height(100.0);
         ui.centered_and_justified(|ui| {
@@ -38,6 +43,27 @@ impl Sidebar {
             if let Some(r2) = app.r2 {
                 ui.label(format!("R²: {:.5}", r2));
             }
+            if let Some(dataset) = &app.dataset {
+                let dropped = dataset.load_report().dropped.len();
+                let label = ui.label(format!("Dataset: {} ({} rows, {} dropped)", dataset.source(), dataset.len(), dropped));
+                if !dataset.load_report().is_complete() {
+                    label.on_hover_text(dataset.load_report().to_string());
+                }
+            }
+            if let Some(validation) = app.validation.as_ref().filter(|validation| !validation.is_clean()) {
+                let label = ui.label(format!(
+                    "Validation: {} findings, {} rows dropped",
+                    validation.findings.len(), validation.rows_dropped
+                ));
+                label.on_hover_text(validation.to_string());
+            }
+            if let Some(warning) = &app.dataset_warning {
+                ui.colored_label(ui.visuals().warn_fg_color, "⚠ Model trained on another dataset")
+                    .on_hover_text(warning);
+            }
+            if let Some(step) = app.last_training_step {
+                ui.label(format!("Iteration {}: cost {:.6}", app.training_iterations, step.cost));
+            }
             ui.add_space(15.0);
         });
       });
@@ -58,6 +84,29 @@ impl Sidebar {
     });
   }
 
+  /// Renders the picker for models stored in the registry.
+  fn render_registry(ui: &mut eframe::egui::Ui, app: &mut App) {
+    let selected = app.registered.as_ref().map_or("Untrained model".to_string(), |entry| entry.id.clone());
+    let mut chosen = None;
+    ui.horizontal(|ui| {
+      ui.label("Registered model:");
+      egui::ComboBox::from_id_salt("registry")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+          for (entry, tags) in &app.registry_entries {
+            let label = if tags.is_empty() { entry.id.clone() } else { format!("{} [{}]", entry.id, tags.join(", ")) };
+            let current = app.registered.as_ref().is_some_and(|registered| registered.id == entry.id);
+            if ui.selectable_label(current, label).on_hover_text(entry.to_string()).clicked() {
+              chosen = Some(entry.id.clone());
+            }
+          }
+        });
+    });
+    if let Some(id) = chosen {
+      app.load_registered_model(&id);
+    }
+  }
+
   /// Renders the controls for grid settings.
   fn render_grid_settings(ui: &mut eframe::egui::Ui, app: &mut App) {
     let grid_settings = app.get_grid_settings();
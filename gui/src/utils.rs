@@ -13,7 +13,7 @@ pub fn to_rgb_color(color: [f32; 3]) -> Color32 {
 
 // Format a large number with sufix
 pub fn format_large_number(value: i64) -> String { 
-  let abs_value = value.abs() as u64;
+  let abs_value = value.unsigned_abs();
   let formatted = match abs_value {
     1_000_000.. => format!("{:.1}m", abs_value as f64 / 1_000_000.0),
     1_000.. => format!("{:.1}k", abs_value as f64 / 1_000.0),
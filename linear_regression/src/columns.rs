@@ -0,0 +1,161 @@
+use serde::{Serialize, Deserialize};
+use crate::float::Float;
+use crate::loss::Loss;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Number of independent accumulators in the sequential kernels, so the
+/// compiler can keep them in vector registers.
+const LANES: usize = 8;
+
+/// Rows handed to each rayon task.
+#[cfg(feature = "parallel")]
+const CHUNK_SIZE: usize = 4096;
+
+/// Datasets smaller than this are not worth splitting across threads.
+#[cfg(feature = "parallel")]
+const PARALLEL_THRESHOLD: usize = 16_384;
+
+/// Struct-of-arrays storage for `(mileage, price)` samples.
+///
+/// Keeping each column contiguous lets the cost and gradient kernels stream
+/// over plain slices instead of interleaved tuples.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "T: Float")]
+pub struct Columns<T: Float = f64> {
+  pub x: Vec<T>,
+  pub y: Vec<T>,
+}
+
+impl<T: Float> Columns<T> {
+  /// Splits `(x, y)` pairs into columns
+  pub fn from_pairs(data: &[(T, T)]) -> Self {
+    let (x, y) = data.iter().copied().unzip();
+    Self { x, y }
+  }
+
+  /// Returns the samples as `(x, y)` pairs
+  pub fn to_pairs(&self) -> Vec<(T, T)> {
+    self.iter().collect()
+  }
+
+  /// Iterates over the samples as `(x, y)` pairs
+  pub fn iter(&self) -> impl Iterator<Item = (T, T)> + '_ {
+    self.x.iter().copied().zip(self.y.iter().copied())
+  }
+
+  /// Appends the samples of `other`
+  pub fn extend(&mut self, other: Columns<T>) {
+    self.x.extend(other.x);
+    self.y.extend(other.y);
+  }
+
+  /// Drops all but the last `n` samples
+  pub fn keep_last(&mut self, n: usize) {
+    let excess = self.len().saturating_sub(n);
+    self.x.drain(..excess);
+    self.y.drain(..excess);
+  }
+
+  pub fn len(&self) -> usize {
+    self.x.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.x.is_empty()
+  }
+
+  /// Σ loss(θ₀ + θ₁·x − y), in parallel when enabled and worthwhile.
+  pub fn loss_sum(&self, theta0: T, theta1: T, loss: Loss) -> T {
+    #[cfg(feature = "parallel")]
+    if self.len() >= PARALLEL_THRESHOLD {
+      return self.loss_sum_parallel(theta0, theta1, loss);
+    }
+    self.loss_sum_sequential(theta0, theta1, loss)
+  }
+
+  /// (Σ loss′(e), Σ loss′(e)·x) with e = θ₀ + θ₁·x − y, in parallel when
+  /// enabled and worthwhile.
+  pub fn gradient_sum(&self, theta0: T, theta1: T, loss: Loss) -> (T, T) {
+    #[cfg(feature = "parallel")]
+    if self.len() >= PARALLEL_THRESHOLD {
+      return self.gradient_sum_parallel(theta0, theta1, loss);
+    }
+    self.gradient_sum_sequential(theta0, theta1, loss)
+  }
+
+  /// Single-threaded `loss_sum`
+  pub fn loss_sum_sequential(&self, theta0: T, theta1: T, loss: Loss) -> T {
+    loss_kernel(&self.x, &self.y, theta0, theta1, loss)
+  }
+
+  /// Single-threaded `gradient_sum`
+  pub fn gradient_sum_sequential(&self, theta0: T, theta1: T, loss: Loss) -> (T, T) {
+    gradient_kernel(&self.x, &self.y, theta0, theta1, loss)
+  }
+
+  /// `loss_sum` split across the rayon thread pool
+  #[cfg(feature = "parallel")]
+  pub fn loss_sum_parallel(&self, theta0: T, theta1: T, loss: Loss) -> T {
+    self.x
+      .par_chunks(CHUNK_SIZE)
+      .zip(self.y.par_chunks(CHUNK_SIZE))
+      .map(|(x, y)| loss_kernel(x, y, theta0, theta1, loss))
+      .sum()
+  }
+
+  /// `gradient_sum` split across the rayon thread pool
+  #[cfg(feature = "parallel")]
+  pub fn gradient_sum_parallel(&self, theta0: T, theta1: T, loss: Loss) -> (T, T) {
+    self.x
+      .par_chunks(CHUNK_SIZE)
+      .zip(self.y.par_chunks(CHUNK_SIZE))
+      .map(|(x, y)| gradient_kernel(x, y, theta0, theta1, loss))
+      .reduce(|| (T::zero(), T::zero()), |a, b| (a.0 + b.0, a.1 + b.1))
+  }
+}
+
+/// Σ loss(θ₀ + θ₁·x − y) over two equally long slices.
+fn loss_kernel<T: Float>(x: &[T], y: &[T], theta0: T, theta1: T, loss: Loss) -> T {
+  let mut lanes = [T::zero(); LANES];
+
+  let x_chunks = x.chunks_exact(LANES);
+  let y_chunks = y.chunks_exact(LANES);
+  let (x_rest, y_rest) = (x_chunks.remainder(), y_chunks.remainder());
+
+  for (xs, ys) in x_chunks.zip(y_chunks) {
+    for lane in 0..LANES {
+      lanes[lane] += loss.value(theta0 + theta1 * xs[lane] - ys[lane]);
+    }
+  }
+  for (&xi, &yi) in x_rest.iter().zip(y_rest) {
+    lanes[0] += loss.value(theta0 + theta1 * xi - yi);
+  }
+
+  lanes.iter().copied().sum()
+}
+
+/// (Σ loss′(e), Σ loss′(e)·x) over two equally long slices.
+fn gradient_kernel<T: Float>(x: &[T], y: &[T], theta0: T, theta1: T, loss: Loss) -> (T, T) {
+  let mut lanes0 = [T::zero(); LANES];
+  let mut lanes1 = [T::zero(); LANES];
+
+  let x_chunks = x.chunks_exact(LANES);
+  let y_chunks = y.chunks_exact(LANES);
+  let (x_rest, y_rest) = (x_chunks.remainder(), y_chunks.remainder());
+
+  for (xs, ys) in x_chunks.zip(y_chunks) {
+    for lane in 0..LANES {
+      let error = loss.derivative(theta0 + theta1 * xs[lane] - ys[lane]);
+      lanes0[lane] += error;
+      lanes1[lane] += error * xs[lane];
+    }
+  }
+  for (&xi, &yi) in x_rest.iter().zip(y_rest) {
+    let error = loss.derivative(theta0 + theta1 * xi - yi);
+    lanes0[0] += error;
+    lanes1[0] += error * xi;
+  }
+
+  (lanes0.iter().copied().sum(), lanes1.iter().copied().sum())
+}
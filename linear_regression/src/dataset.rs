@@ -0,0 +1,386 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::error::{LinearRegressionError, Result};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use crate::float::Float;
+use crate::utils::{embedded_sample, get_dataset_fallback, get_dataset_path, get_load_options, read_dataset_file};
+use crate::parsing::{LoadOptions, LoadReport};
+use crate::formats::{write_dataset, DatasetFormat};
+use crate::describe::{correlation, Description, Summary};
+use crate::validation::{ValidationReport, ValidationRules};
+use crate::imputation::Imputer;
+
+/// Where a dataset's samples came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DatasetSource {
+  /// A CSV file at the given path
+  File(String),
+  /// The sample bundled with the crate
+  EmbeddedSample,
+  /// Samples built in code
+  #[default]
+  InMemory,
+}
+
+impl fmt::Display for DatasetSource {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DatasetSource::File(path) => write!(f, "file '{}'", path),
+      DatasetSource::EmbeddedSample => write!(f, "embedded sample"),
+      DatasetSource::InMemory => write!(f, "in-memory samples"),
+    }
+  }
+}
+
+/// What `Dataset::load` does when the dataset file does not exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fallback {
+  /// Fail with `DatasetNotFound`
+  #[default]
+  Strict,
+  /// Use the embedded sample instead
+  EmbeddedSample,
+}
+
+impl FromStr for Fallback {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_ascii_lowercase().as_str() {
+      "strict" => Ok(Fallback::Strict),
+      "sample" => Ok(Fallback::EmbeddedSample),
+      other => Err(format!("Unknown dataset fallback '{}', expected strict or sample", other)),
+    }
+  }
+}
+
+/// Name and optional unit of a dataset column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Column {
+  pub name: String,
+  pub unit: Option<String>,
+}
+
+impl Column {
+  pub fn new(name: impl Into<String>, unit: Option<&str>) -> Self {
+    Self { name: name.into(), unit: unit.map(str::to_string) }
+  }
+
+  /// Parses a header such as `price`, `price (EUR)` or `mileage [km]`.
+  pub fn from_header(header: &str) -> Self {
+    let header = header.trim();
+    for (open, close) in [('(', ')'), ('[', ']')] {
+      if let Some(start) = header.rfind(open) {
+        if header.ends_with(close) && start > 0 {
+          let unit = header[start + 1..header.len() - 1].trim();
+          return Self::new(header[..start].trim(), (!unit.is_empty()).then_some(unit));
+        }
+      }
+    }
+    Self::new(header, None)
+  }
+
+  /// The default feature column, mileage
+  pub fn km() -> Self {
+    Self::new("km", None)
+  }
+
+  /// The default target column
+  pub fn price() -> Self {
+    Self::new("price", None)
+  }
+}
+
+impl fmt::Display for Column {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.unit {
+      Some(unit) => write!(f, "{} ({})", self.name, unit),
+      None => write!(f, "{}", self.name),
+    }
+  }
+}
+
+/// Identifies the samples a model was trained on, so a changed dataset
+/// can be noticed later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatasetFingerprint {
+  pub rows: usize,
+  /// SHA-256 of the samples' values, in hex
+  pub sha256: String,
+  pub source: DatasetSource,
+  /// Set when the rows were hashed as `StreamingTrainer` reads them:
+  /// complete rows only, neither imputed nor validated
+  #[serde(default)]
+  pub streamed: bool,
+}
+
+impl DatasetFingerprint {
+  /// True when both fingerprints hash the same values, wherever they were read from
+  pub fn matches(&self, other: &DatasetFingerprint) -> bool {
+    self.rows == other.rows && self.sha256 == other.sha256
+  }
+
+  /// The first 12 hex digits of the hash, for display
+  pub fn short_hash(&self) -> &str {
+    &self.sha256[..self.sha256.len().min(12)]
+  }
+}
+
+impl fmt::Display for DatasetFingerprint {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} rows from {} (sha256 {})", self.rows, self.source, self.short_hash())
+  }
+}
+
+/// Hashes samples as they are read, so datasets streamed in chunks get
+/// the same fingerprint as when loaded at once.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Fingerprinter {
+  hasher: Sha256,
+  rows: usize,
+}
+
+impl Fingerprinter {
+  pub(crate) fn update<T: Float>(&mut self, records: &[(T, T)]) {
+    for &(x, y) in records {
+      self.hasher.update(x.as_f64().to_le_bytes());
+      self.hasher.update(y.as_f64().to_le_bytes());
+    }
+    self.rows += records.len();
+  }
+
+  pub(crate) fn finish(self, source: DatasetSource) -> DatasetFingerprint {
+    let sha256 = self.hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+    DatasetFingerprint { rows: self.rows, sha256, source, streamed: false }
+  }
+}
+
+/// How the dataset a model was trained on compares with the current one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatasetMatch {
+  /// Same values as when the model was trained
+  Same,
+  /// The values changed since the model was trained
+  Changed { trained: DatasetFingerprint, current: DatasetFingerprint },
+  /// The model does not record what it was trained on
+  Unknown,
+}
+
+impl DatasetMatch {
+  /// Compares the fingerprint a model was saved with to the current dataset's
+  pub fn of(trained: Option<&DatasetFingerprint>, current: DatasetFingerprint) -> Self {
+    match trained {
+      Some(trained) if trained.matches(&current) => DatasetMatch::Same,
+      Some(trained) => DatasetMatch::Changed { trained: trained.clone(), current },
+      None => DatasetMatch::Unknown,
+    }
+  }
+
+  /// A warning to show when the model is stale
+  pub fn warning(&self) -> Option<String> {
+    match self {
+      DatasetMatch::Changed { trained, current } => Some(format!(
+        "The model was trained on {}, but the dataset now has {}. Retrain it to use the current data.",
+        trained, current
+      )),
+      _ => None,
+    }
+  }
+}
+
+/// In-memory `(feature, target)` samples a model can be fitted on, along
+/// with their column names and where each row came from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "T: Float")]
+pub struct Dataset<T: Float = f64> {
+  records: Vec<(T, T)>,
+  source: DatasetSource,
+  feature: Column,
+  target: Column,
+  /// Line of the source file each record was read from, empty for other sources
+  lines: Vec<usize>,
+  /// Rows read from the source file and the ones dropped
+  report: LoadReport,
+  /// Fill values used for missing cells while loading
+  #[serde(default)]
+  imputation: Option<Imputer>,
+}
+
+impl<T: Float> Default for Dataset<T> {
+  fn default() -> Self {
+    Self::new(Vec::new())
+  }
+}
+
+impl<T: Float> Dataset<T> {
+  /// Wraps samples that are already in memory
+  pub fn new(records: Vec<(T, T)>) -> Self {
+    Self::with_source(records, DatasetSource::InMemory)
+  }
+
+  fn with_source(records: Vec<(T, T)>, source: DatasetSource) -> Self {
+    let report = LoadReport { rows: records.len(), ..LoadReport::default() };
+    Self {
+      records,
+      source,
+      feature: Column::km(),
+      target: Column::price(),
+      lines: Vec::new(),
+      report,
+      imputation: None,
+    }
+  }
+
+  /// Names the feature and target columns
+  pub fn with_columns(mut self, feature: Column, target: Column) -> Self {
+    self.feature = feature;
+    self.target = target;
+    self
+  }
+
+  /// Loads the dataset configured by `DATASET_PATH`, read as described by
+  /// `get_load_options`, falling back to the embedded sample only if
+  /// `DATASET_FALLBACK=sample`
+  pub fn load() -> Result<Self> {
+    Self::load_from(&get_dataset_path()?, get_dataset_fallback()?, &get_load_options()?)
+  }
+
+  /// Loads the dataset file at `path`. With `Fallback::EmbeddedSample` a missing
+  /// file yields the embedded sample; unreadable rows are never hidden this way.
+  pub fn load_from(path: &str, fallback: Fallback, options: &LoadOptions) -> Result<Self> {
+    match Self::from_file_with(path, options) {
+      Err(LinearRegressionError::DatasetNotFound { .. }) if fallback == Fallback::EmbeddedSample => Ok(Self::sample()),
+      result => result,
+    }
+  }
+
+  /// Loads a dataset file whose header names the feature and target
+  /// columns. CSV, TSV, JSON and NDJSON are told apart by extension.
+  pub fn from_file(path: &str) -> Result<Self> {
+    Self::from_file_with(path, &LoadOptions::default())
+  }
+
+  /// Loads the columns of a dataset file selected by `options`
+  pub fn from_file_with(path: &str, options: &LoadOptions) -> Result<Self> {
+    let file = read_dataset_file(path, options)?;
+    let column = |header: &str, default: Column| if header.is_empty() { default } else { Column::from_header(header) };
+    let columns = (column(&file.headers.0, Column::km()), column(&file.headers.1, Column::price()));
+    Ok(Self {
+      lines: file.lines,
+      report: file.report,
+      imputation: file.imputation,
+      ..Self::with_source(file.records, DatasetSource::File(path.to_string())).with_columns(columns.0, columns.1)
+    })
+  }
+
+  /// The sample dataset bundled with the crate
+  pub fn sample() -> Self {
+    Self::with_source(embedded_sample(), DatasetSource::EmbeddedSample)
+  }
+
+  /// Returns the samples as `(mileage, price)` pairs
+  pub fn records(&self) -> &[(T, T)] {
+    &self.records
+  }
+
+  /// Writes the dataset to `path`, in the format its extension names
+  pub fn save(&self, path: &str) -> Result<()> {
+    write_dataset(path, self, DatasetFormat::from_path(path))
+  }
+
+  /// Where the samples came from
+  pub fn source(&self) -> &DatasetSource {
+    &self.source
+  }
+
+  /// The feature column (mileage by default)
+  pub fn feature(&self) -> &Column {
+    &self.feature
+  }
+
+  /// The target column (price by default)
+  pub fn target(&self) -> &Column {
+    &self.target
+  }
+
+  /// Line of the source file record `index` was read from
+  pub fn line(&self, index: usize) -> Option<usize> {
+    self.lines.get(index).copied()
+  }
+
+  /// Rows read from the source and the ones dropped, with the reason
+  pub fn load_report(&self) -> &LoadReport {
+    &self.report
+  }
+
+  /// Fill values used for missing cells while loading, so the same
+  /// imputation can be replayed at prediction time
+  pub fn imputation(&self) -> Option<&Imputer> {
+    self.imputation.as_ref()
+  }
+
+  /// Hashes the samples' values, independently of the file format they
+  /// were read from.
+  pub fn fingerprint(&self) -> DatasetFingerprint {
+    let mut fingerprinter = Fingerprinter::default();
+    fingerprinter.update(&self.records);
+    fingerprinter.finish(self.source.clone())
+  }
+
+  /// Count, mean, standard deviation, min/max and quartiles of both
+  /// columns, and their correlation.
+  pub fn describe(&self) -> Description<T> {
+    let (x, y): (Vec<T>, Vec<T>) = self.records.iter().copied().unzip();
+    Description {
+      feature_name: self.feature.to_string(),
+      target_name: self.target.to_string(),
+      feature: Summary::of(&x),
+      target: Summary::of(&y),
+      correlation: correlation(&x, &y),
+    }
+  }
+
+  /// Keeps the rows whose index `keep` accepts, with their line numbers,
+  /// columns and source. The load report counts the kept rows, and only
+  /// lists the cells imputed in them.
+  pub fn filter_rows(&self, keep: impl Fn(usize) -> bool) -> Self {
+    let rows: Vec<usize> = (0..self.records.len()).filter(|&row| keep(row)).collect();
+    let lines: Vec<usize> = rows.iter().filter_map(|&row| self.line(row)).collect();
+    let report = LoadReport {
+      rows: rows.len(),
+      dropped: self.report.dropped.clone(),
+      // Lines are in file order
+      imputed: self.report.imputed.iter().filter(|value| lines.binary_search(&value.line).is_ok()).cloned().collect(),
+    };
+
+    Self {
+      records: rows.iter().map(|&row| self.records[row]).collect(),
+      source: self.source.clone(),
+      feature: self.feature.clone(),
+      target: self.target.clone(),
+      lines,
+      report,
+      imputation: self.imputation,
+    }
+  }
+
+  /// Checks the rows against `rules`, see `ValidationRules::clean`
+  pub fn clean(&self, rules: &ValidationRules) -> (Self, ValidationReport) {
+    rules.clean(self)
+  }
+
+  pub fn len(&self) -> usize {
+    self.records.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.records.is_empty()
+  }
+}
+
+impl<T: Float> From<Vec<(T, T)>> for Dataset<T> {
+  fn from(records: Vec<(T, T)>) -> Self {
+    Self::new(records)
+  }
+}
@@ -0,0 +1,118 @@
+use std::cmp::Ordering;
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::float::Float;
+
+/// Summary statistics of one column.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "T: Float")]
+pub struct Summary<T: Float = f64> {
+  pub count: usize,
+  pub mean: T,
+  /// Sample standard deviation (n − 1 denominator)
+  pub std: T,
+  pub min: T,
+  /// First quartile
+  pub q1: T,
+  pub median: T,
+  /// Third quartile
+  pub q3: T,
+  pub max: T,
+}
+
+impl<T: Float> Summary<T> {
+  /// Summarizes `values`. All statistics are NaN when there are none.
+  pub fn of(values: &[T]) -> Self {
+    let count = values.len();
+    if count == 0 {
+      let nan = T::nan();
+      return Self { count, mean: nan, std: nan, min: nan, q1: nan, median: nan, q3: nan, max: nan };
+    }
+
+    let n = T::of(count as f64);
+    let mean = values.iter().copied().sum::<T>() / n;
+    let std = if count > 1 {
+      (values.iter().map(|&v| (v - mean).powi(2)).sum::<T>() / (n - T::one())).sqrt()
+    } else {
+      T::zero()
+    };
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    Self {
+      count,
+      mean,
+      std,
+      min: sorted[0],
+      q1: quantile(&sorted, 0.25),
+      median: quantile(&sorted, 0.5),
+      q3: quantile(&sorted, 0.75),
+      max: sorted[count - 1],
+    }
+  }
+
+  /// Interquartile range, q3 − q1
+  pub fn iqr(&self) -> T {
+    self.q3 - self.q1
+  }
+}
+
+/// Quantile `q` of sorted, non-empty `values`, interpolating linearly between ranks.
+pub fn quantile<T: Float>(sorted: &[T], q: f64) -> T {
+  let rank = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+  let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+  let weight = T::of(rank - lower as f64);
+  sorted[lower] + (sorted[upper] - sorted[lower]) * weight
+}
+
+/// Pearson correlation between two columns of equal length.
+/// NaN when either column is constant or there are fewer than two values.
+pub fn correlation<T: Float>(x: &[T], y: &[T]) -> T {
+  if x.len() < 2 || x.len() != y.len() {
+    return T::nan();
+  }
+
+  let n = T::of(x.len() as f64);
+  let mean_x = x.iter().copied().sum::<T>() / n;
+  let mean_y = y.iter().copied().sum::<T>() / n;
+  let (mut covariance, mut var_x, mut var_y) = (T::zero(), T::zero(), T::zero());
+  for (&xi, &yi) in x.iter().zip(y) {
+    covariance += (xi - mean_x) * (yi - mean_y);
+    var_x += (xi - mean_x).powi(2);
+    var_y += (yi - mean_y).powi(2);
+  }
+  covariance / (var_x * var_y).sqrt()
+}
+
+/// What `Dataset::describe` reports: a summary of each column and their correlation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "T: Float")]
+pub struct Description<T: Float = f64> {
+  pub feature_name: String,
+  pub target_name: String,
+  pub feature: Summary<T>,
+  pub target: Summary<T>,
+  pub correlation: T,
+}
+
+impl<T: Float> fmt::Display for Description<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "{:<8}{:>16}{:>16}", "", self.feature_name, self.target_name)?;
+    writeln!(f, "{:<8}{:>16}{:>16}", "count", self.feature.count, self.target.count)?;
+
+    let rows = [
+      ("mean", self.feature.mean, self.target.mean),
+      ("std", self.feature.std, self.target.std),
+      ("min", self.feature.min, self.target.min),
+      ("25%", self.feature.q1, self.target.q1),
+      ("50%", self.feature.median, self.target.median),
+      ("75%", self.feature.q3, self.target.q3),
+      ("max", self.feature.max, self.target.max),
+    ];
+    for (label, feature, target) in rows {
+      writeln!(f, "{:<8}{:>16.2}{:>16.2}", label, feature, target)?;
+    }
+    write!(f, "correlation: {:.4}", self.correlation)
+  }
+}
@@ -0,0 +1,347 @@
+use std::fmt;
+use std::io::Read;
+use serde::{Serialize, Deserialize};
+use crate::error::{LinearRegressionError, Result};
+use crate::float::Float;
+use crate::utils::open_dataset_file;
+
+/// Number of equal-frequency bins the training distribution is summarized
+/// in: its deciles.
+pub const DISTRIBUTION_BINS: usize = 10;
+
+/// Bins of the fixed-width histogram quantiles are read from.
+const SKETCH_BINS: usize = 1024;
+
+/// Smallest bin proportion used in the PSI, so empty bins stay finite.
+const PSI_FLOOR: f64 = 1e-4;
+
+/// Halvings of the mileage range when locating a merged quantile.
+const MERGE_BISECTIONS: usize = 64;
+
+/// Compact summary of the mileages a model was trained on: the quantiles
+/// splitting them into `DISTRIBUTION_BINS` bins of equal frequency, from
+/// the minimum to the maximum. Between quantiles the distribution is taken
+/// as uniform.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureDistribution {
+  /// Training samples summarized
+  pub count: usize,
+  /// `DISTRIBUTION_BINS + 1` increasing quantiles, the first the minimum
+  /// and the last the maximum
+  pub quantiles: Vec<f64>,
+}
+
+impl FeatureDistribution {
+  /// Summarizes `values`. `None` when there are none.
+  pub fn of<T: Float>(values: &[T]) -> Option<Self> {
+    let min = values.iter().map(|v| v.as_f64()).fold(f64::INFINITY, f64::min);
+    let max = values.iter().map(|v| v.as_f64()).fold(f64::NEG_INFINITY, f64::max);
+    let mut sketch = DistributionSketch::new(min, max);
+    sketch.update(values.iter().copied());
+    sketch.finish()
+  }
+
+  /// The summary of the training samples and `values` together, for
+  /// models that keep learning. The samples summarized are taken as
+  /// uniform between quantiles.
+  pub fn merged<T: Float>(&self, values: &[T]) -> Self {
+    let mut sorted: Vec<f64> = values.iter().map(|v| v.as_f64()).collect();
+    sorted.sort_by(f64::total_cmp);
+    let (Some(&first), Some(&last)) = (sorted.first(), sorted.last()) else {
+      return self.clone();
+    };
+    let count = self.count + sorted.len();
+    let cdf = |x: f64| {
+      (self.count as f64 * self.cdf(x) + sorted.partition_point(|&v| v <= x) as f64) / count as f64
+    };
+    let (min, max) = (first.min(self.quantiles[0]), last.max(self.quantiles[self.quantiles.len() - 1]));
+    // The combined CDF is monotone, so each quantile is found by bisection
+    let quantile = |p: f64| {
+      let (mut low, mut high) = (min, max);
+      for _ in 0..MERGE_BISECTIONS {
+        let mid = low + (high - low) / 2.0;
+        if cdf(mid) >= p {
+          high = mid;
+        } else {
+          low = mid;
+        }
+      }
+      high
+    };
+    let mut quantiles: Vec<f64> = (0..=DISTRIBUTION_BINS).map(|i| quantile(i as f64 / DISTRIBUTION_BINS as f64)).collect();
+    quantiles[0] = min;
+    quantiles[DISTRIBUTION_BINS] = max;
+    Self { count, quantiles }
+  }
+
+  /// Share of the training samples at or below `x`
+  pub fn cdf(&self, x: f64) -> f64 {
+    let q = &self.quantiles;
+    // Last quantile at or below x
+    match q.partition_point(|&quantile| quantile <= x) {
+      0 => 0.0,
+      i if i == q.len() => 1.0,
+      i => self.interpolate(i - 1, x),
+    }
+  }
+
+  /// Share of the training samples strictly below `x`
+  fn cdf_below(&self, x: f64) -> f64 {
+    let q = &self.quantiles;
+    // First quantile at or above x
+    match q.partition_point(|&quantile| quantile < x) {
+      0 => 0.0,
+      i if i == q.len() => 1.0,
+      i => self.interpolate(i - 1, x),
+    }
+  }
+
+  /// The CDF on the segment from quantile `i` to quantile `i + 1`
+  fn interpolate(&self, i: usize, x: f64) -> f64 {
+    let (low, high) = (self.quantiles[i], self.quantiles[i + 1]);
+    let within = if high > low { (x - low) / (high - low) } else { 1.0 };
+    (i as f64 + within) / self.bins() as f64
+  }
+
+  fn bins(&self) -> usize {
+    self.quantiles.len().saturating_sub(1).max(1)
+  }
+
+  /// Population stability index of `sorted` against the training deciles:
+  /// Σ (batch − training) · ln(batch / training) over the bins.
+  pub fn psi(&self, sorted: &[f64]) -> f64 {
+    let n = sorted.len() as f64;
+    // Bins end at each distinct inner quantile; repeated quantiles merge bins
+    let mut edges: Vec<f64> = self.quantiles[1..self.quantiles.len() - 1].to_vec();
+    edges.dedup();
+
+    let (mut psi, mut previous_training, mut previous_batch) = (0.0, 0.0, 0.0);
+    for edge in edges.into_iter().map(Some).chain([None]) {
+      let (training, batch) = match edge {
+        Some(edge) => (self.cdf(edge), sorted.partition_point(|&x| x <= edge) as f64 / n),
+        None => (1.0, 1.0),
+      };
+      let expected = (training - previous_training).max(PSI_FLOOR);
+      let actual = (batch - previous_batch).max(PSI_FLOOR);
+      psi += (actual - expected) * (actual / expected).ln();
+      (previous_training, previous_batch) = (training, batch);
+    }
+    psi
+  }
+
+  /// Two-sample Kolmogorov–Smirnov statistic of `sorted` against the
+  /// training distribution: the largest gap between their CDFs.
+  pub fn ks_statistic(&self, sorted: &[f64]) -> f64 {
+    let n = sorted.len() as f64;
+    // Both CDFs are monotone, and the batch's only steps at its values, so
+    // the gap is largest on either side of a batch value or a quantile
+    sorted
+      .iter()
+      .chain(&self.quantiles)
+      .map(|&x| {
+        let at = sorted.partition_point(|&v| v <= x) as f64 / n;
+        let below = sorted.partition_point(|&v| v < x) as f64 / n;
+        (self.cdf(x) - at).abs().max((self.cdf_below(x) - below).abs())
+      })
+      .fold(0.0, f64::max)
+  }
+}
+
+/// Accumulates a fixed-width histogram over a known range, so the
+/// quantiles of data read in chunks can be estimated in bounded memory.
+#[derive(Debug, Clone)]
+pub(crate) struct DistributionSketch {
+  min: f64,
+  max: f64,
+  counts: Vec<u64>,
+}
+
+impl DistributionSketch {
+  /// A sketch of values between `min` and `max`; others are counted in
+  /// the first or last bin.
+  pub(crate) fn new(min: f64, max: f64) -> Self {
+    Self { min, max, counts: vec![0; SKETCH_BINS] }
+  }
+
+  pub(crate) fn update<T: Float>(&mut self, values: impl IntoIterator<Item = T>) {
+    let width = (self.max - self.min) / SKETCH_BINS as f64;
+    for value in values {
+      let bin = if width > 0.0 { ((value.as_f64() - self.min) / width).floor() } else { 0.0 };
+      self.counts[(bin.max(0.0) as usize).min(SKETCH_BINS - 1)] += 1;
+    }
+  }
+
+  /// The deciles of the values seen, `None` when there were none.
+  pub(crate) fn finish(&self) -> Option<FeatureDistribution> {
+    let count: u64 = self.counts.iter().sum();
+    if count == 0 {
+      return None;
+    }
+    let width = (self.max - self.min) / SKETCH_BINS as f64;
+    // Values are taken as uniform within a bin
+    let quantile = |p: f64| {
+      let rank = p * count as f64;
+      let mut below = 0;
+      for (bin, &in_bin) in self.counts.iter().enumerate() {
+        if in_bin > 0 && (below + in_bin) as f64 >= rank {
+          let within = (rank - below as f64) / in_bin as f64;
+          return self.min + (bin as f64 + within) * width;
+        }
+        below += in_bin;
+      }
+      self.max
+    };
+    let mut quantiles: Vec<f64> = (0..=DISTRIBUTION_BINS).map(|i| quantile(i as f64 / DISTRIBUTION_BINS as f64)).collect();
+    quantiles[0] = self.min;
+    quantiles[DISTRIBUTION_BINS] = self.max;
+    Some(FeatureDistribution { count: count as usize, quantiles })
+  }
+}
+
+/// When a batch of inputs counts as drifted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriftThresholds {
+  /// PSI at or above which the batch has drifted. Below 0.1 is usually
+  /// read as stable, and from 0.25 as a significant shift.
+  pub psi: f64,
+  /// Significance level of the Kolmogorov–Smirnov test
+  pub ks_alpha: f64,
+  /// Inputs a batch needs for its PSI to count. Smaller batches leave
+  /// deciles empty by chance, which inflates the PSI; the KS test accounts
+  /// for the batch size itself.
+  pub psi_min_count: usize,
+}
+
+impl Default for DriftThresholds {
+  fn default() -> Self {
+    Self { psi: 0.25, ks_alpha: 0.05, psi_min_count: 50 }
+  }
+}
+
+/// How a batch of inputs compares to the training distribution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriftReport {
+  /// Inputs in the batch
+  pub count: usize,
+  /// Population stability index over the training deciles
+  pub psi: f64,
+  /// Whether the batch was large enough for the PSI to count
+  pub psi_counted: bool,
+  /// Largest gap between the training and batch CDFs
+  pub ks_statistic: f64,
+  /// Probability of a gap this large if the batch came from the training
+  /// distribution
+  pub ks_p_value: f64,
+  /// Inputs below the lowest or above the highest training mileage
+  pub out_of_range: usize,
+  /// Whether either statistic crossed its threshold
+  pub drifted: bool,
+}
+
+impl fmt::Display for DriftReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "Drift of {} inputs against the training mileages:", self.count)?;
+    if self.psi_counted {
+      writeln!(f, "  PSI: {:.4}", self.psi)?;
+    } else {
+      writeln!(f, "  PSI: {:.4} (too few inputs to count)", self.psi)?;
+    }
+    writeln!(f, "  Kolmogorov–Smirnov: D = {:.4}, p = {:.4}", self.ks_statistic, self.ks_p_value)?;
+    writeln!(f, "  Outside the training range: {}", self.out_of_range)?;
+    if self.drifted {
+      write!(f, "Drift detected: the inputs no longer look like the training data.")
+    } else {
+      write!(f, "No drift detected.")
+    }
+  }
+}
+
+/// Compares batches of prediction inputs with the distribution a model was
+/// trained on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriftMonitor {
+  pub distribution: FeatureDistribution,
+  pub thresholds: DriftThresholds,
+}
+
+impl DriftMonitor {
+  pub fn new(distribution: FeatureDistribution, thresholds: DriftThresholds) -> Self {
+    Self { distribution, thresholds }
+  }
+
+  /// Computes the PSI and Kolmogorov–Smirnov statistics of `batch`.
+  /// Returns `None` for an empty batch, or a distribution without quantiles.
+  pub fn check<T: Float>(&self, batch: &[T]) -> Option<DriftReport> {
+    if batch.is_empty() || self.distribution.quantiles.len() < 2 {
+      return None;
+    }
+    let mut sorted: Vec<f64> = batch.iter().map(|x| x.as_f64()).collect();
+    // A total order keeps the batch sorted even with NaNs, which go last
+    sorted.sort_by(f64::total_cmp);
+
+    let distribution = &self.distribution;
+    let psi = distribution.psi(&sorted);
+    let ks_statistic = distribution.ks_statistic(&sorted);
+    let (n, m) = (sorted.len() as f64, distribution.count.max(1) as f64);
+    let ks_p_value = kolmogorov_p_value(ks_statistic, n * m / (n + m));
+    let (min, max) = (distribution.quantiles[0], distribution.quantiles[distribution.quantiles.len() - 1]);
+    let out_of_range = sorted.iter().filter(|&&x| x < min || x > max).count();
+    let psi_counted = sorted.len() >= self.thresholds.psi_min_count;
+
+    Some(DriftReport {
+      count: sorted.len(),
+      psi,
+      psi_counted,
+      ks_statistic,
+      ks_p_value,
+      out_of_range,
+      drifted: (psi_counted && psi >= self.thresholds.psi) || ks_p_value < self.thresholds.ks_alpha,
+    })
+  }
+}
+
+/// Asymptotic p-value of the Kolmogorov–Smirnov statistic `d` for `n`
+/// effective samples, with Stephens' small-sample correction.
+fn kolmogorov_p_value(d: f64, n: f64) -> f64 {
+  let lambda = (n.sqrt() + 0.12 + 0.11 / n.sqrt()) * d;
+  if lambda < 1e-3 {
+    return 1.0;
+  }
+  let mut sum = 0.0;
+  for k in 1..=100 {
+    let term = (-2.0 * (k * k) as f64 * lambda * lambda).exp();
+    sum += if k % 2 == 1 { term } else { -term };
+    if term < 1e-12 {
+      break;
+    }
+  }
+  (2.0 * sum).clamp(0.0, 1.0)
+}
+
+/// Reads prediction inputs from `path`: one mileage per line, or the first
+/// field of each comma-separated line. A header line is skipped.
+pub fn read_inputs<T: Float>(path: &str) -> Result<Vec<T>> {
+  let mut contents = String::new();
+  open_dataset_file(path)?.read_to_string(&mut contents)?;
+
+  let mut inputs = Vec::new();
+  for (index, line) in contents.lines().enumerate() {
+    let field = line.split(',').next().unwrap_or_default().trim();
+    if field.is_empty() {
+      continue;
+    }
+    match field.parse::<T>() {
+      Ok(value) if value.is_finite() => inputs.push(value),
+      Err(_) if index == 0 => {}
+      _ => {
+        return Err(LinearRegressionError::Parse {
+          path: path.to_string(),
+          row: index + 1,
+          column: Some(1),
+          message: format!("'{}' is not a mileage", field),
+        })
+      }
+    }
+  }
+  Ok(inputs)
+}
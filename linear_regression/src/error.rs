@@ -0,0 +1,132 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong while loading data, training, or
+/// reading and writing a saved model.
+#[derive(Debug)]
+pub enum LinearRegressionError {
+  /// The dataset file does not exist
+  DatasetNotFound { path: String },
+  /// A dataset row could not be read. `row` is the line number in the
+  /// file (the header is line 1); `column` is 1-based when known.
+  Parse { path: String, row: usize, column: Option<usize>, message: String },
+  /// There are no samples to train on
+  EmptyDataset,
+  /// A sample given for training holds NaN or an infinity; `index` is its
+  /// position in the batch
+  NonFiniteSample { index: usize },
+  /// Every sample has the same mileage, so no slope can be fitted
+  DegenerateFeatureRange { value: f64 },
+  /// Training diverged; usually the learning rate is too high
+  NonFiniteCost { iteration: usize },
+  /// A model holds NaN or an infinity where a number must be written out,
+  /// e.g. when exporting it; `name` says which value
+  NonFiniteParameter { name: String },
+  /// A prediction was asked of a model that has not been trained
+  Untrained,
+  /// The model file does not exist
+  ModelNotFound { path: String },
+  /// The parameter file exists but cannot be understood
+  CorruptParameterFile { path: String, reason: String },
+  /// The parameter file was written in a different format or precision
+  VersionMismatch { path: String, expected: String, found: String },
+  /// A model registry operation failed, e.g. an unknown tag or id
+  Registry { path: String, message: String },
+  /// An environment variable holds an unusable value
+  InvalidConfig { variable: String, message: String },
+  Io(io::Error),
+}
+
+/// Result type used across the crate.
+pub type Result<T, E = LinearRegressionError> = std::result::Result<T, E>;
+
+impl LinearRegressionError {
+  /// A short suggestion for fixing the error, for display next to it.
+  pub fn hint(&self) -> &'static str {
+    match self {
+      Self::DatasetNotFound { .. } => "Check the file path, or DATASET_PATH in your .env file.",
+      Self::Parse { .. } => "Make sure every row holds two numbers: km,price.",
+      Self::EmptyDataset => "Add at least two samples with different mileages to the dataset.",
+      Self::NonFiniteSample { .. } => "Remove NaN and infinite values from the samples.",
+      Self::DegenerateFeatureRange { .. } => "Add samples with different mileages to the dataset.",
+      Self::NonFiniteCost { .. } => "Lower the learning rate and train again.",
+      Self::NonFiniteParameter { .. } => "Train the model again, with a lower learning rate if it diverged.",
+      Self::Untrained => "Run the trainer first, or load a trained model.",
+      Self::ModelNotFound { .. } => "Run the trainer to create it, or check MODEL_PATH in your .env file.",
+      Self::CorruptParameterFile { .. } => "Delete the parameter file and run the trainer again.",
+      Self::VersionMismatch { .. } => "Run the trainer again to save the model in the expected format.",
+      Self::Registry { .. } => "Run `make registry` to list the registered models and their tags.",
+      Self::InvalidConfig { .. } => "Fix the value in your .env file.",
+      Self::Io(_) => "Check that the files exist and are readable and writable.",
+    }
+  }
+}
+
+impl LinearRegressionError {
+  /// Wraps an error reading or writing the CSV file at `path`, keeping the
+  /// line and field it occurred at.
+  pub(crate) fn csv(path: &str, err: csv::Error) -> Self {
+    let row = err.position().map_or(0, |position| position.line() as usize);
+    let column = match err.kind() {
+      csv::ErrorKind::Deserialize { err, .. } => err.field().map(|field| field as usize + 1),
+      csv::ErrorKind::Utf8 { err, .. } => Some(err.field() + 1),
+      _ => None,
+    };
+    let message = err.to_string();
+    match err.into_kind() {
+      csv::ErrorKind::Io(err) => Self::Io(err),
+      _ => Self::Parse { path: path.to_string(), row, column, message },
+    }
+  }
+
+  /// Wraps an error reading or writing the JSON model file at `path`. The
+  /// reason names the line and column the JSON stopped making sense.
+  pub(crate) fn json(path: &str, err: serde_json::Error) -> Self {
+    if err.is_io() {
+      Self::Io(err.into())
+    } else {
+      Self::CorruptParameterFile { path: path.to_string(), reason: err.to_string() }
+    }
+  }
+}
+
+impl fmt::Display for LinearRegressionError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::DatasetNotFound { path } => write!(f, "Dataset '{}' not found", path),
+      Self::Parse { path, row, column: Some(column), message } =>
+        write!(f, "{}: row {}, column {}: {}", path, row, column, message),
+      Self::Parse { path, row, column: None, message } => write!(f, "{}: row {}: {}", path, row, message),
+      Self::EmptyDataset => write!(f, "The dataset has no samples"),
+      Self::NonFiniteSample { index } => write!(f, "Sample {} holds a NaN or infinite value", index),
+      Self::DegenerateFeatureRange { value } =>
+        write!(f, "Every sample has a mileage of {}, the feature has no range", value),
+      Self::NonFiniteCost { iteration } => write!(f, "Cost became NaN or infinite at iteration {}", iteration),
+      Self::NonFiniteParameter { name } => write!(f, "The model's {} is NaN or infinite", name),
+      Self::Untrained => write!(f, "The model has not been trained yet"),
+      Self::ModelNotFound { path } => write!(f, "Model file '{}' not found", path),
+      Self::CorruptParameterFile { path, reason } => write!(f, "Parameter file '{}' is corrupt: {}", path, reason),
+      Self::VersionMismatch { path, expected, found } =>
+        write!(f, "Parameter file '{}' was saved as {}, expected {}", path, found, expected),
+      Self::Registry { path, message } => write!(f, "Model registry '{}': {}", path, message),
+      Self::InvalidConfig { variable, message } => write!(f, "Invalid {}: {}", variable, message),
+      Self::Io(err) => write!(f, "{}", err),
+    }
+  }
+}
+
+impl Error for LinearRegressionError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      Self::Io(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for LinearRegressionError {
+  fn from(err: io::Error) -> Self {
+    Self::Io(err)
+  }
+}
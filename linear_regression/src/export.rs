@@ -0,0 +1,251 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::error::{LinearRegressionError, Result};
+use crate::float::Float;
+use crate::linear_regression::LinearRegression;
+use crate::normalization::Scaler;
+use crate::prediction::ClampPolicy;
+
+/// Languages a fitted model can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+  Rust,
+  C,
+  Python,
+  JavaScript,
+  /// A column expression for a `SELECT`
+  Sql,
+  /// The equation, for documents
+  Latex,
+}
+
+impl Language {
+  /// Every language, in the order they are documented
+  pub const ALL: [Language; 6] =
+    [Language::Rust, Language::C, Language::Python, Language::JavaScript, Language::Sql, Language::Latex];
+}
+
+impl FromStr for Language {
+  type Err = String;
+
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    match s.trim().to_ascii_lowercase().as_str() {
+      "rust" | "rs" => Ok(Language::Rust),
+      "c" => Ok(Language::C),
+      "python" | "py" => Ok(Language::Python),
+      "javascript" | "js" => Ok(Language::JavaScript),
+      "sql" => Ok(Language::Sql),
+      "latex" | "tex" => Ok(Language::Latex),
+      other => Err(format!("Unknown language '{}', expected rust, c, python, javascript, sql or latex", other)),
+    }
+  }
+}
+
+impl fmt::Display for Language {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Language::Rust => write!(f, "rust"),
+      Language::C => write!(f, "c"),
+      Language::Python => write!(f, "python"),
+      Language::JavaScript => write!(f, "javascript"),
+      Language::Sql => write!(f, "sql"),
+      Language::Latex => write!(f, "latex"),
+    }
+  }
+}
+
+/// Names used in the exported code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+  /// Name of the generated function, in snake case. JavaScript gets it in
+  /// camel case.
+  pub function_name: String,
+  /// Name of the input: the parameter, or the SQL column
+  pub feature: String,
+  /// Name of the output: the SQL alias, or the left-hand side in LaTeX
+  pub target: String,
+}
+
+impl Default for ExportOptions {
+  fn default() -> Self {
+    Self {
+      function_name: "predict_price".to_string(),
+      feature: "km".to_string(),
+      target: "price".to_string(),
+    }
+  }
+}
+
+/// Tokens that differ between the languages' arithmetic.
+struct Syntax {
+  times: &'static str,
+  open: &'static str,
+  close: &'static str,
+}
+
+impl Syntax {
+  fn of(language: Language) -> Self {
+    match language {
+      Language::Latex => Syntax { times: " \\cdot ", open: "\\left(", close: "\\right)" },
+      _ => Syntax { times: " * ", open: "(", close: ")" },
+    }
+  }
+
+  /// Writes `value` so it reads back exactly, as a floating point literal
+  /// in every language, and parenthesized when negative. No language spells
+  /// NaN or the infinities the same way, so those fail with
+  /// `NonFiniteParameter`, naming the value as `name`.
+  fn number(&self, name: &str, value: f64) -> Result<String> {
+    check_finite(name, value)?;
+    let mut literal = value.to_string();
+    if !literal.contains('.') {
+      literal.push_str(".0");
+    }
+    if value.is_sign_negative() {
+      Ok(format!("{}{}{}", self.open, literal, self.close))
+    } else {
+      Ok(literal)
+    }
+  }
+}
+
+/// The price on `model`'s fitted line for `feature`, as an expression in
+/// `language`. Normalization is spelled out, so the expression computes
+/// what `LinearRegression::predict_unchecked` does, step by step.
+///
+/// Fails with `NonFiniteParameter` when a parameter or normalization factor
+/// is NaN or infinite.
+pub fn formula<T: Float>(model: &LinearRegression<T>, language: Language, feature: &str) -> Result<String> {
+  let syntax = Syntax::of(language);
+  let factors = model.get_normalization();
+  let (theta0, theta1) = model.get_params();
+  let number = |name: &str, value: T| syntax.number(name, value.as_f64());
+  let variable = match language {
+    Language::Latex => format!("\\mathrm{{{}}}", feature),
+    _ => feature.to_string(),
+  };
+
+  let x = match factors.scaler {
+    Scaler::None => variable,
+    _ => format!(
+      "{}{} - {}{} / {}",
+      syntax.open,
+      variable,
+      number("mileage offset", factors.x_offset())?,
+      syntax.close,
+      number("mileage span", factors.x_span())?
+    ),
+  };
+  let line = format!("{} + {}{}{}", number("theta0", theta0)?, number("theta1", theta1)?, syntax.times, x);
+  Ok(match factors.scaler {
+    Scaler::None => line,
+    _ => format!(
+      "{}{}{}{}{} + {}",
+      syntax.open,
+      line,
+      syntax.close,
+      syntax.times,
+      number("price span", factors.y_span())?,
+      number("price offset", factors.y_offset())?
+    ),
+  })
+}
+
+/// Standalone source for `model` in `language`: a function taking the
+/// feature and returning the prediction, a SQL column expression, or a
+/// LaTeX equation. The model's clamp policy is applied to the formula.
+/// Fails like `formula`.
+pub fn export<T: Float>(model: &LinearRegression<T>, language: Language, options: &ExportOptions) -> Result<String> {
+  let ExportOptions { function_name, feature, target } = options;
+  let formula = clamped(language, formula(model, language, feature)?, model.get_clamp())?;
+  let summary = format!("Predicts {} from {}, as fitted by ft_linear_regression.", target, feature);
+
+  Ok(match language {
+    Language::Rust => format!(
+      "/// {}\npub fn {}({}: f64) -> f64 {{\n    {}\n}}\n",
+      summary, function_name, feature, formula
+    ),
+    Language::C if !model.get_clamp().is_none() => format!(
+      "#include <math.h>\n\n/* {} */\ndouble {}(double {}) {{\n    return {};\n}}\n",
+      summary, function_name, feature, formula
+    ),
+    Language::C => format!(
+      "/* {} */\ndouble {}(double {}) {{\n    return {};\n}}\n",
+      summary, function_name, feature, formula
+    ),
+    Language::Python => format!(
+      "def {}({}: float) -> float:\n    \"\"\"{}\"\"\"\n    return {}\n",
+      function_name, feature, summary, formula
+    ),
+    Language::JavaScript => format!(
+      "/** {} */\nfunction {}({}) {{\n  return {};\n}}\n",
+      summary, camel_case(function_name), feature, formula
+    ),
+    Language::Sql => format!("-- {}\n{} AS {}\n", summary, formula, target),
+    Language::Latex => format!("\\hat{{\\mathrm{{{}}}}} = {}\n", target, formula),
+  })
+}
+
+/// Wraps `expression` in the maximum with the floor and the minimum with
+/// the ceiling of `clamp`, when they are set. SQL gets a `CASE`, as SQLite
+/// has no `GREATEST` or `LEAST`.
+fn clamped(language: Language, expression: String, clamp: ClampPolicy) -> Result<String> {
+  let syntax = Syntax::of(language);
+  let floor = clamp.floor.map(|floor| syntax.number("clamp floor", floor)).transpose()?;
+  let ceiling = clamp.ceiling.map(|ceiling| syntax.number("clamp ceiling", ceiling)).transpose()?;
+
+  if language == Language::Sql {
+    if clamp.is_none() {
+      return Ok(expression);
+    }
+    let mut case = String::from("CASE");
+    if let Some(floor) = floor {
+      case.push_str(&format!(" WHEN {} < {} THEN {}", expression, floor, floor));
+    }
+    if let Some(ceiling) = ceiling {
+      case.push_str(&format!(" WHEN {} > {} THEN {}", expression, ceiling, ceiling));
+    }
+    return Ok(format!("{} ELSE {} END", case, expression));
+  }
+
+  let bound = |function: &str, expression: String, limit: String| match language {
+    Language::Rust => format!("({}).{}({})", expression, function, limit),
+    Language::C => format!("f{}({}, {})", function, expression, limit),
+    Language::Python => format!("{}({}, {})", function, expression, limit),
+    Language::JavaScript => format!("Math.{}({}, {})", function, expression, limit),
+    Language::Latex => format!("\\{}\\left({}, {}\\right)", function, expression, limit),
+    Language::Sql => unreachable!("SQL is clamped with CASE"),
+  };
+  let expression = match floor {
+    Some(floor) => bound("max", expression, floor),
+    None => expression,
+  };
+  Ok(match ceiling {
+    Some(ceiling) => bound("min", expression, ceiling),
+    None => expression,
+  })
+}
+
+/// Fails with `NonFiniteParameter`, naming `value` as `name`, when it is
+/// NaN or infinite: exported models must be readable by other tools.
+pub(crate) fn check_finite(name: &str, value: f64) -> Result<()> {
+  if value.is_finite() {
+    Ok(())
+  } else {
+    Err(LinearRegressionError::NonFiniteParameter { name: name.to_string() })
+  }
+}
+
+/// `predict_price` → `predictPrice`
+fn camel_case(name: &str) -> String {
+  let mut words = name.split('_').filter(|word| !word.is_empty());
+  let mut camel = words.next().unwrap_or_default().to_string();
+  for word in words {
+    let mut chars = word.chars();
+    if let Some(first) = chars.next() {
+      camel.extend(first.to_uppercase());
+      camel.push_str(chars.as_str());
+    }
+  }
+  camel
+}
@@ -0,0 +1,75 @@
+use std::fmt::{self, Debug, Display};
+use std::iter::Sum;
+use std::num::ParseFloatError;
+use std::str::FromStr;
+use num_traits::FromPrimitive;
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+/// Floating point precision a model is trained and stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Precision {
+  F32,
+  #[default]
+  F64,
+}
+
+impl Display for Precision {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Precision::F32 => write!(f, "f32"),
+      Precision::F64 => write!(f, "f64"),
+    }
+  }
+}
+
+impl FromStr for Precision {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_ascii_lowercase().as_str() {
+      "f32" => Ok(Precision::F32),
+      "f64" => Ok(Precision::F64),
+      other => Err(format!("Unknown precision '{}', expected f32 or f64", other)),
+    }
+  }
+}
+
+/// Scalar type the model, normalization and datasets are generic over.
+/// Implemented for `f32` and `f64`.
+pub trait Float:
+  num_traits::Float
+  + num_traits::NumAssign
+  + FromPrimitive
+  + FromStr<Err = ParseFloatError>
+  + Sum
+  + Default
+  + Debug
+  + Display
+  + Serialize
+  + DeserializeOwned
+  + Send
+  + Sync
+  + 'static
+{
+  /// Precision tag recorded in saved models
+  const PRECISION: Precision;
+
+  /// Converts an `f64` constant to this type
+  fn of(value: f64) -> Self {
+    Self::from_f64(value).expect("f64 is representable in every Float type")
+  }
+
+  /// Converts to `f64` for reporting and export
+  fn as_f64(self) -> f64 {
+    self.to_f64().expect("Float types always convert to f64")
+  }
+}
+
+impl Float for f32 {
+  const PRECISION: Precision = Precision::F32;
+}
+
+impl Float for f64 {
+  const PRECISION: Precision = Precision::F64;
+}
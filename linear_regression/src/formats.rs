@@ -0,0 +1,248 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use csv::{ReaderBuilder, WriterBuilder};
+use serde_json::{Map, Value};
+use crate::dataset::Dataset;
+use crate::error::{LinearRegressionError, Result};
+use crate::float::Float;
+use crate::parsing::{apply_policy, ColumnSelector, DroppedRow, LoadOptions, LoadReport, RecordParser};
+use crate::utils::open_dataset_file;
+use crate::imputation::{MissingValues, PartialRow};
+
+/// File formats datasets can be read from and written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DatasetFormat {
+  #[default]
+  Csv,
+  Tsv,
+  /// A JSON array of objects (or of `[feature, target]` arrays)
+  Json,
+  /// One JSON object per line (JSON Lines)
+  Ndjson,
+}
+
+impl DatasetFormat {
+  /// Detects the format from the file extension, defaulting to CSV.
+  pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+    let extension = path.as_ref().extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+    match extension.to_ascii_lowercase().as_str() {
+      "tsv" | "tab" => DatasetFormat::Tsv,
+      "json" => DatasetFormat::Json,
+      "ndjson" | "jsonl" => DatasetFormat::Ndjson,
+      _ => DatasetFormat::Csv,
+    }
+  }
+}
+
+impl fmt::Display for DatasetFormat {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DatasetFormat::Csv => write!(f, "csv"),
+      DatasetFormat::Tsv => write!(f, "tsv"),
+      DatasetFormat::Json => write!(f, "json"),
+      DatasetFormat::Ndjson => write!(f, "ndjson"),
+    }
+  }
+}
+
+impl FromStr for DatasetFormat {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_ascii_lowercase().as_str() {
+      "csv" => Ok(DatasetFormat::Csv),
+      "tsv" => Ok(DatasetFormat::Tsv),
+      "json" => Ok(DatasetFormat::Json),
+      "ndjson" | "jsonl" => Ok(DatasetFormat::Ndjson),
+      other => Err(format!("Unknown dataset format '{}', expected csv, tsv, json or ndjson", other)),
+    }
+  }
+}
+
+/// Calls `f` with the line and values of every row of the dataset at `path`,
+/// in the format set in `options` or detected from the extension. Cells are
+/// `None` only when missing values are not configured to fail. Returns
+/// the header names of the selected columns (empty when the file has none)
+/// and what was read and dropped.
+pub(crate) fn for_each_row<T, F>(path: &str, options: &LoadOptions, f: F) -> Result<((String, String), LoadReport)>
+where
+  T: Float,
+  F: FnMut(usize, PartialRow<T>),
+{
+  match options.format.unwrap_or_else(|| DatasetFormat::from_path(path)) {
+    DatasetFormat::Csv => csv_rows(path, options, options.delimiter, f),
+    DatasetFormat::Tsv => csv_rows(path, options, b'\t', f),
+    DatasetFormat::Json => json_rows(path, options, f),
+    DatasetFormat::Ndjson => ndjson_rows(path, options, f),
+  }
+}
+
+fn csv_rows<T, F>(path: &str, options: &LoadOptions, delimiter: u8, mut f: F) -> Result<((String, String), LoadReport)>
+where
+  T: Float,
+  F: FnMut(usize, PartialRow<T>),
+{
+  let mut reader = ReaderBuilder::new()
+    .has_headers(options.has_headers)
+    .delimiter(delimiter)
+    .quote(options.quote)
+    .comment(options.comment)
+    .flexible(true)
+    .from_reader(open_dataset_file(path)?);
+  let headers = options
+    .has_headers
+    .then(|| reader.headers().cloned())
+    .transpose()
+    .map_err(|err| LinearRegressionError::csv(path, err))?;
+  let parser = RecordParser::new(path, options, headers.as_ref())?;
+
+  let mut report = LoadReport::default();
+  for result in reader.records() {
+    if let Some((line, record)) = parser.parse(result, &mut report)? {
+      f(line, record);
+    }
+  }
+  Ok((headers.map(|headers| parser.headers(&headers)).unwrap_or_default(), report))
+}
+
+fn json_rows<T, F>(path: &str, options: &LoadOptions, mut f: F) -> Result<((String, String), LoadReport)>
+where
+  T: Float,
+  F: FnMut(usize, PartialRow<T>),
+{
+  let rows: Vec<Value> = serde_json::from_reader(BufReader::new(open_dataset_file(path)?)).map_err(|err| {
+    LinearRegressionError::Parse {
+      path: path.to_string(),
+      row: err.line(),
+      column: Some(err.column()),
+      message: err.to_string(),
+    }
+  })?;
+
+  let headers = rows.first().map(|row| json_headers(row, options)).unwrap_or_default();
+  let mut report = LoadReport::default();
+  // JSON arrays have no meaningful lines; rows are numbered from 1 instead
+  for (index, row) in rows.iter().enumerate() {
+    if let Some(record) = apply_policy(path, options, json_record(index + 1, row, options), &mut report)? {
+      f(index + 1, record);
+    }
+  }
+  Ok((headers, report))
+}
+
+fn ndjson_rows<T, F>(path: &str, options: &LoadOptions, mut f: F) -> Result<((String, String), LoadReport)>
+where
+  T: Float,
+  F: FnMut(usize, PartialRow<T>),
+{
+  let reader = BufReader::new(open_dataset_file(path)?);
+  let mut headers = None;
+  let mut report = LoadReport::default();
+
+  for (index, line) in reader.lines().enumerate() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let row = serde_json::from_str::<Value>(&line).map_err(|err| DroppedRow {
+      line: index + 1,
+      column: Some(err.column()),
+      reason: err.to_string(),
+    });
+    if let Ok(row) = &row {
+      headers.get_or_insert_with(|| json_headers(row, options));
+    }
+
+    let record = row.and_then(|row| json_record(index + 1, &row, options));
+    if let Some(record) = apply_policy(path, options, record, &mut report)? {
+      f(index + 1, record);
+    }
+  }
+  Ok((headers.unwrap_or_default(), report))
+}
+
+/// Names of the selected fields, as found in the first object
+fn json_headers(row: &Value, options: &LoadOptions) -> (String, String) {
+  let header = |selector: &ColumnSelector| match (selector, row) {
+    (ColumnSelector::Name(name), _) => name.clone(),
+    (ColumnSelector::Index(index), Value::Object(fields)) => fields.keys().nth(*index).cloned().unwrap_or_default(),
+    _ => String::new(),
+  };
+  (header(&options.feature), header(&options.target))
+}
+
+/// Extracts the selected fields from an object (by key, or by position
+/// in the order they appear) or from an array (by index).
+fn json_record<T: Float>(line: usize, row: &Value, options: &LoadOptions) -> Result<PartialRow<T>, DroppedRow> {
+  let field = |selector: &ColumnSelector| -> Result<Option<T>, DroppedRow> {
+    let dropped = |reason: String| DroppedRow {
+      line,
+      column: match selector {
+        ColumnSelector::Index(index) => Some(index + 1),
+        ColumnSelector::Name(_) => None,
+      },
+      reason: format!("field {}: {}", selector, reason),
+    };
+
+    let value = match (row, selector) {
+      (Value::Object(fields), ColumnSelector::Name(name)) => fields.get(name),
+      (Value::Object(fields), ColumnSelector::Index(index)) => fields.values().nth(*index),
+      (Value::Array(values), ColumnSelector::Index(index)) => values.get(*index),
+      (Value::Array(_), ColumnSelector::Name(_)) => return Err(dropped("rows are arrays, select fields by index".to_string())),
+      _ => return Err(dropped("expected an object or an array".to_string())),
+    };
+    match value {
+      None | Some(Value::Null) if options.missing != MissingValues::Fail => Ok(None),
+      None | Some(Value::Null) => Err(dropped("missing value".to_string())),
+      Some(Value::Number(number)) => number
+        .as_f64()
+        .map(|value| Some(T::of(value)))
+        .ok_or_else(|| dropped(format!("{} is out of range", number))),
+      Some(Value::String(text)) => options.parse_cell(Some(text)).map_err(dropped),
+      Some(other) => Err(dropped(format!("{} is not a number", other))),
+    }
+  };
+
+  Ok((field(&options.feature)?, field(&options.target)?))
+}
+
+/// Writes `dataset` to `path` in `format`, naming the columns after the
+/// dataset's feature and target columns.
+pub fn write_dataset<T: Float>(path: &str, dataset: &Dataset<T>, format: DatasetFormat) -> Result<()> {
+  let names = (dataset.feature().to_string(), dataset.target().to_string());
+
+  match format {
+    DatasetFormat::Csv | DatasetFormat::Tsv => {
+      let delimiter = if format == DatasetFormat::Tsv { b'\t' } else { b',' };
+      let csv_error = |err| LinearRegressionError::csv(path, err);
+      let mut writer = WriterBuilder::new().delimiter(delimiter).from_path(path).map_err(csv_error)?;
+      writer.write_record([&names.0, &names.1]).map_err(csv_error)?;
+      for (x, y) in dataset.records() {
+        writer.write_record([x.to_string(), y.to_string()]).map_err(csv_error)?;
+      }
+      writer.flush()?;
+    }
+    DatasetFormat::Json => {
+      let rows: Vec<_> = dataset.records().iter().map(|&row| json_object(&names, row)).collect();
+      serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &rows).map_err(io::Error::from)?;
+    }
+    DatasetFormat::Ndjson => {
+      let mut writer = BufWriter::new(File::create(path)?);
+      for &row in dataset.records() {
+        writeln!(writer, "{}", Value::Object(json_object(&names, row)))?;
+      }
+      writer.flush()?;
+    }
+  }
+  Ok(())
+}
+
+fn json_object<T: Float>((feature, target): &(String, String), (x, y): (T, T)) -> Map<String, Value> {
+  let mut object = Map::new();
+  object.insert(feature.clone(), Value::from(x.as_f64()));
+  object.insert(target.clone(), Value::from(y.as_f64()));
+  object
+}
@@ -0,0 +1,41 @@
+use crate::float::Float;
+
+/// Result of comparing an analytic gradient against central finite differences.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientCheck<T: Float = f64> {
+  pub analytic: (T, T),
+  pub numeric: (T, T),
+  /// ‖analytic − numeric‖ / (‖analytic‖ + ‖numeric‖)
+  pub relative_error: T,
+}
+
+impl<T: Float> GradientCheck<T> {
+  /// True when the relative error is below `tolerance`
+  pub fn passed(&self, tolerance: T) -> bool {
+    self.relative_error < tolerance
+  }
+}
+
+/// Checks `gradient` against central finite differences of `cost` at `theta`.
+///
+/// Works with any cost/gradient pair, so custom losses can be validated
+/// before they are used for training.
+pub fn check_gradient<T, C, G>(cost: C, gradient: G, theta: (T, T), epsilon: T) -> GradientCheck<T>
+where
+  T: Float,
+  C: Fn(T, T) -> T,
+  G: Fn(T, T) -> (T, T),
+{
+  let (theta0, theta1) = theta;
+  let analytic = gradient(theta0, theta1);
+  let numeric = (
+    (cost(theta0 + epsilon, theta1) - cost(theta0 - epsilon, theta1)) / (epsilon + epsilon),
+    (cost(theta0, theta1 + epsilon) - cost(theta0, theta1 - epsilon)) / (epsilon + epsilon),
+  );
+
+  let difference = (analytic.0 - numeric.0).hypot(analytic.1 - numeric.1);
+  let scale = analytic.0.hypot(analytic.1) + numeric.0.hypot(numeric.1);
+  let relative_error = if scale == T::zero() { T::zero() } else { difference / scale };
+
+  GradientCheck { analytic, numeric, relative_error }
+}
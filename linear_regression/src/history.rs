@@ -0,0 +1,94 @@
+use crate::error::{LinearRegressionError, Result};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use csv::Writer;
+use serde::{Serialize, Deserialize};
+use crate::float::Float;
+use crate::observer::TrainingStep;
+
+/// Per-step record of a model's gradient descent, for plotting descent
+/// paths and convergence. All vectors have one entry per step.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "T: Float")]
+pub struct TrainingHistory<T: Float = f64> {
+  pub costs: Vec<T>,
+  pub theta0: Vec<T>,
+  pub theta1: Vec<T>,
+  pub gradient_norms: Vec<T>,
+  pub learning_rates: Vec<T>,
+}
+
+/// One row of an exported history.
+#[derive(Serialize)]
+#[serde(bound = "T: Float")]
+struct HistoryRow<T: Float> {
+  iteration: usize,
+  cost: T,
+  theta0: T,
+  theta1: T,
+  gradient_norm: T,
+  learning_rate: T,
+}
+
+impl<T: Float> TrainingHistory<T> {
+  /// Appends a step. θ is recorded as it was before the step was applied.
+  pub fn record(&mut self, step: &TrainingStep<T>) {
+    self.costs.push(step.cost);
+    self.theta0.push(step.theta.0);
+    self.theta1.push(step.theta.1);
+    self.gradient_norms.push(step.gradient_norm());
+    self.learning_rates.push(step.learning_rate);
+  }
+
+  pub fn len(&self) -> usize {
+    self.costs.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.costs.is_empty()
+  }
+
+  /// Cost of the most recent step
+  pub fn last_cost(&self) -> Option<T> {
+    self.costs.last().copied()
+  }
+
+  /// Writes one `iteration,cost,theta0,theta1,gradient_norm,learning_rate` row per step.
+  pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    let path = path.as_ref().display().to_string();
+    let mut writer = Writer::from_path(&path).map_err(|err| LinearRegressionError::csv(&path, err))?;
+    for row in self.rows() {
+      writer.serialize(row).map_err(|err| LinearRegressionError::csv(&path, err))?;
+    }
+    writer.flush()?;
+    Ok(())
+  }
+
+  /// Writes the history as a JSON array of per-step objects.
+  pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    let rows: Vec<HistoryRow<T>> = self.rows().collect();
+    serde_json::to_writer_pretty(File::create(path)?, &rows).map_err(io::Error::from)?;
+    Ok(())
+  }
+
+  /// Writes CSV or JSON depending on the file extension (`.json` or anything else).
+  pub fn export<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    let path = path.as_ref();
+    match path.extension().and_then(|ext| ext.to_str()) {
+      Some(ext) if ext.eq_ignore_ascii_case("json") => self.write_json(path),
+      _ => self.write_csv(path),
+    }
+  }
+
+  fn rows(&self) -> impl Iterator<Item = HistoryRow<T>> + '_ {
+    (0..self.len()).map(|i| HistoryRow {
+      iteration: i,
+      cost: self.costs[i],
+      theta0: self.theta0[i],
+      theta1: self.theta1[i],
+      gradient_norm: self.gradient_norms[i],
+      learning_rate: self.learning_rates[i],
+    })
+  }
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::describe::{quantile, Summary};
+use crate::float::Float;
+use crate::parsing::{DroppedRow, ImputedValue, LoadReport};
+
+/// A row as read from a dataset file, with `None` for missing cells.
+pub(crate) type PartialRow<T> = (Option<T>, Option<T>);
+
+/// A complete row and the line of the file it was read from.
+type NumberedRow<T> = (usize, (T, T));
+
+/// How rows with an empty feature or target cell are handled while loading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MissingValues {
+  /// Treat the row as unparsable, so the bad row policy applies
+  #[default]
+  Fail,
+  /// Leave the row out and record it in the `LoadReport`
+  Drop,
+  /// Fill the cell with the mean of its column
+  Mean,
+  /// Fill the cell with the median of its column
+  Median,
+  /// Predict the cell from the other column, with a least-squares line
+  /// fitted on the complete rows
+  Regression,
+}
+
+impl FromStr for MissingValues {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_ascii_lowercase().as_str() {
+      "fail" => Ok(MissingValues::Fail),
+      "drop" => Ok(MissingValues::Drop),
+      "mean" => Ok(MissingValues::Mean),
+      "median" => Ok(MissingValues::Median),
+      "regression" => Ok(MissingValues::Regression),
+      other => Err(format!("Unknown missing value strategy '{}', expected fail, drop, mean, median or regression", other)),
+    }
+  }
+}
+
+impl fmt::Display for MissingValues {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      MissingValues::Fail => write!(f, "fail"),
+      MissingValues::Drop => write!(f, "drop"),
+      MissingValues::Mean => write!(f, "mean"),
+      MissingValues::Median => write!(f, "median"),
+      MissingValues::Regression => write!(f, "regression"),
+    }
+  }
+}
+
+/// Fill values fitted on a dataset, kept with the model so the same
+/// imputation can be replayed at prediction time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Imputer {
+  pub strategy: MissingValues,
+  /// Value used for a missing feature: the column mean, or median
+  pub feature_fill: f64,
+  /// Value used for a missing target: the column mean, or median
+  pub target_fill: f64,
+  /// `(intercept, slope)` of the line predicting the feature from the target
+  pub feature_from_target: Option<(f64, f64)>,
+  /// `(intercept, slope)` of the line predicting the target from the feature
+  pub target_from_feature: Option<(f64, f64)>,
+}
+
+impl Imputer {
+  /// Fits fill values on the cells present in `rows`. Returns `None` for
+  /// strategies that never fill a cell.
+  pub fn fit<T: Float>(strategy: MissingValues, rows: &[PartialRow<T>]) -> Option<Self> {
+    let present = |column: fn(&PartialRow<T>) -> Option<T>| -> Vec<f64> {
+      rows.iter().filter_map(column).map(T::as_f64).collect()
+    };
+    let (x, y) = (present(|row| row.0), present(|row| row.1));
+    let fill = |values: &[f64]| match strategy {
+      MissingValues::Median => {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        quantile(&sorted, 0.5)
+      }
+      _ => Summary::of(values).mean,
+    };
+
+    let complete: Vec<(f64, f64)> = rows
+      .iter()
+      .filter_map(|&(x, y)| Some((x?.as_f64(), y?.as_f64())))
+      .collect();
+    let regression = strategy == MissingValues::Regression;
+
+    match strategy {
+      MissingValues::Fail | MissingValues::Drop => None,
+      _ => Some(Self {
+        strategy,
+        feature_fill: fill(&x),
+        target_fill: fill(&y),
+        feature_from_target: regression.then(|| least_squares(complete.iter().map(|&(x, y)| (y, x)))).flatten(),
+        target_from_feature: regression.then(|| least_squares(complete.iter().copied())).flatten(),
+      }),
+    }
+  }
+
+  /// Value for a missing feature, predicted from `target` when regression
+  /// imputation was fitted and the target is known.
+  pub fn feature<T: Float>(&self, target: Option<T>) -> T {
+    T::of(predict_or(self.feature_from_target, target, self.feature_fill))
+  }
+
+  /// Value for a missing target, predicted from `feature` when regression
+  /// imputation was fitted and the feature is known.
+  pub fn target<T: Float>(&self, feature: Option<T>) -> T {
+    T::of(predict_or(self.target_from_feature, feature, self.target_fill))
+  }
+
+  /// Completes `row`. Returns `None` when both cells are missing, or no
+  /// finite value could be computed.
+  pub fn impute<T: Float>(&self, row: PartialRow<T>) -> Option<(T, T)> {
+    let (x, y) = match row {
+      (None, None) => return None,
+      (x, y) => (x.unwrap_or_else(|| self.feature(y)), y.unwrap_or_else(|| self.target(x))),
+    };
+    (x.is_finite() && y.is_finite()).then_some((x, y))
+  }
+}
+
+fn predict_or<T: Float>(line: Option<(f64, f64)>, value: Option<T>, fallback: f64) -> f64 {
+  match (line, value) {
+    (Some((intercept, slope)), Some(value)) => intercept + slope * value.as_f64(),
+    _ => fallback,
+  }
+}
+
+/// `(intercept, slope)` of the least-squares line through `points`, or
+/// `None` with fewer than two distinct `x` values.
+fn least_squares(points: impl Iterator<Item = (f64, f64)> + Clone) -> Option<(f64, f64)> {
+  let n = points.clone().count() as f64;
+  let (sum_x, sum_y) = points.clone().fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+  let (mean_x, mean_y) = (sum_x / n, sum_y / n);
+  let (covariance, variance) = points.fold((0.0, 0.0), |(c, v), (x, y)| {
+    (c + (x - mean_x) * (y - mean_y), v + (x - mean_x) * (x - mean_x))
+  });
+  (variance > 0.0).then(|| {
+    let slope = covariance / variance;
+    (mean_y - slope * mean_x, slope)
+  })
+}
+
+/// Completes the rows read from a file with the `strategy` for missing
+/// values. Rows that stay incomplete are moved to the dropped rows of
+/// `report`, and every filled cell is recorded in it.
+pub(crate) fn complete_rows<T: Float>(
+  strategy: MissingValues,
+  rows: Vec<(usize, PartialRow<T>)>,
+  columns: (&str, &str),
+  report: &mut LoadReport,
+) -> (Vec<NumberedRow<T>>, Option<Imputer>) {
+  let partial: Vec<PartialRow<T>> = rows.iter().map(|&(_, row)| row).collect();
+  let imputer = Imputer::fit(strategy, &partial);
+  let mut completed = Vec::with_capacity(rows.len());
+
+  for (line, row) in rows {
+    let filled = match row {
+      (Some(x), Some(y)) => Some((x, y)),
+      row => imputer.and_then(|imputer| imputer.impute(row)),
+    };
+    let Some((x, y)) = filled else {
+      report.rows -= 1;
+      report.dropped.push(DroppedRow { line, column: None, reason: "missing value".to_string() });
+      continue;
+    };
+    for (cell, value, column) in [(row.0, x, columns.0), (row.1, y, columns.1)] {
+      if cell.is_none() {
+        report.imputed.push(ImputedValue { line, column: column.to_string(), value: value.as_f64() });
+      }
+    }
+    completed.push((line, (x, y)));
+  }
+  (completed, imputer)
+}
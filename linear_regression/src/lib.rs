@@ -1,19 +1,154 @@
 pub mod linear_regression;
 pub mod utils;
 pub mod normalization;
+pub mod loss;
+pub mod gradient_check;
+pub mod float;
+pub mod columns;
+pub mod sufficient_stats;
+pub mod streaming;
+pub mod observer;
+pub mod history;
+pub mod dataset;
+pub mod describe;
+pub mod parsing;
+pub mod formats;
+pub mod optimizer;
+pub mod error;
+pub mod validation;
+pub mod imputation;
+pub mod model_file;
+pub mod persistence;
+pub mod registry;
+pub mod export;
+pub mod pmml;
+pub mod prediction;
+pub mod drift;
 
 #[cfg(test)]
 mod tests {
   use super::linear_regression::LinearRegression;
-  use super::utils::{load_dataset_file, load_params, save_params};
+  use super::loss::{Loss, Regularization};
+  use super::gradient_check::check_gradient;
+  use super::utils::check_dataset_fingerprint;
+  use super::utils::{detect_parameter_format, migrate_model, read_model, read_model_precision, ParameterFileFormat};
+  use super::utils::{load_dataset_file, load_dataset_from};
+  use super::float::Precision;
+  use super::columns::Columns;
+  use super::streaming::StreamingTrainer;
+  use super::observer::{TrainingControl, TrainingStep};
+  use super::dataset::{Column, Dataset, DatasetMatch, DatasetSource, Fallback};
+  use super::normalization::Scaler;
+  use super::optimizer::{Optimizer, StoppingCriteria};
+  use super::float::Float;
+  use super::parsing::{BadRowPolicy, ColumnSelector, LoadOptions};
+  use super::formats::DatasetFormat;
+  use super::error::LinearRegressionError;
+  use super::imputation::{Imputer, MissingValues};
+  use super::persistence::{backup_path, read_locked, write_atomically};
+  use super::model_file::{model_precision, ModelFile, MODEL_FORMAT_VERSION};
+  use super::registry::{Registry, LATEST, PRODUCTION_TAG};
+  use super::export::{export, formula, ExportOptions, Language};
+  use super::pmml::{from_pmml, load_pmml, save_pmml, to_pmml};
+  use super::prediction::{ClampPolicy, Extrapolation};
+  use super::drift::{read_inputs, DriftMonitor, DriftThresholds, FeatureDistribution, DISTRIBUTION_BINS};
+  use super::validation::{Action, Issue, OutlierMethod, Side, ValidationRules};
+
+  /// An unfitted model attached to the fixture dataset
+  fn fixture_model<T: Float>(learning_rate: T) -> Result<LinearRegression<T>, Box<dyn std::error::Error>> {
+    let mut model = LinearRegression::new(Some(learning_rate));
+    model.set_dataset(&Dataset::load()?)?;
+    Ok(model)
+  }
 
   #[test]
   fn test_training_and_prediction() -> Result<(), Box<dyn std::error::Error>> {
-    let mut model = LinearRegression::new(Some(0.01))?;
-    model.train(1000);
+    let mut model: LinearRegression = fixture_model(0.5)?;
+    model.train(1000)?;
     let (theta0, theta1) = model.get_params();
     assert!((theta0 - 1.0).abs() < 0.1);
-    assert!((theta1 - 1.0).abs() < 0.1);
+    assert!((theta1 + 1.0).abs() < 0.1);
+    assert!((model.predict(30000.0)?.value - 16000.0).abs() < 100.0);
+    Ok(())
+  }
+
+  #[test]
+  fn test_training_in_f32() -> Result<(), Box<dyn std::error::Error>> {
+    let mut model = fixture_model(0.5f32)?;
+    model.train(1000)?;
+    assert!((model.predict(30000.0)?.value - 16000.0).abs() < 100.0);
+
+    let (mae, _, _, r2) = model.compute_precision()?;
+    assert!(mae < 100.0);
+    assert!(r2 > 0.99);
+    assert!(matches!(
+      LinearRegression::<f32>::new(None).compute_precision(),
+      Err(LinearRegressionError::EmptyDataset)
+    ));
+    Ok(())
+  }
+
+  #[test]
+  fn test_partial_fit_rescale_preserves_predictions() -> Result<(), Box<dyn std::error::Error>> {
+    let mut model: LinearRegression = fixture_model(0.5)?;
+    // The fixture's exact fit, so the new sample leaves nothing to learn
+    model.set_params(1.0, -1.0);
+    let before = model.predict(30000.0)?.value;
+
+    // A sample on the fitted line but outside the known range only widens the scale
+    model.partial_fit(&[(80000.0, model.predict(80000.0)?.value)])?;
+
+    assert!((model.predict(30000.0)?.value - before).abs() < 1e-6);
+    assert_eq!(model.get_dataset().len(), 6);
+    Ok(())
+  }
+
+  #[test]
+  fn test_partial_fit_stream() -> Result<(), Box<dyn std::error::Error>> {
+    let mut model: LinearRegression = LinearRegression::new(Some(0.5));
+    let rows = (0..50)
+      .flat_map(|_| (0..=100).map(|i| i as f64 * 1000.0))
+      .map(|km| (km, 22000.0 - 0.2 * km));
+
+    let consumed = model.partial_fit_stream(rows, 10)?;
+
+    assert_eq!(consumed, 50 * 101);
+    assert!((model.predict(75000.0)?.value - 7000.0).abs() < 70.0);
+    Ok(())
+  }
+
+  #[test]
+  fn test_partial_fit_approaches_batch_fit() -> Result<(), Box<dyn std::error::Error>> {
+    let rows: Vec<(f64, f64)> = (0..100)
+      .map(|i| (i as f64 * 1000.0, 22000.0 - 0.2 * i as f64 * 1000.0 + ((i * 7) % 11) as f64 * 100.0 - 500.0))
+      .collect();
+    // Run both to convergence so they are compared at the same optimum
+    let stopping = StoppingCriteria { max_iterations: 10_000, tolerance: 0.0, min_gradient_norm: 1e-9 };
+    let build = || -> LinearRegression { LinearRegression::builder().learning_rate(0.5).stopping(stopping).build() };
+    let mut batch = build();
+    batch.fit(&Dataset::new(rows.clone()))?;
+
+    let mut incremental = build();
+    for chunk in rows.chunks(25) {
+      incremental.partial_fit(chunk)?;
+    }
+
+    for km in [0.0, 50000.0, 99000.0] {
+      assert!((incremental.predict(km)?.value - batch.predict(km)?.value).abs() < 1.0);
+    }
+    assert_eq!(incremental.get_sufficient_stats().n, 100);
+    let (merged, whole) = (incremental.get_feature_distribution(), batch.get_feature_distribution());
+    let (merged, whole) = (merged.ok_or("no distribution")?, whole.ok_or("no distribution")?);
+    assert_eq!(merged.count, 100);
+    for (merged, whole) in merged.quantiles.iter().zip(&whole.quantiles) {
+      assert!((merged - whole).abs() < 1000.0, "{} vs {}", merged, whole);
+    }
+
+    assert!(matches!(
+      incremental.partial_fit(&[(1000.0, 2000.0), (f64::NAN, 1.0)]),
+      Err(LinearRegressionError::NonFiniteSample { index: 1 })
+    ));
+    assert_eq!(incremental.get_dataset().len(), 100);
     Ok(())
   }
 
@@ -25,13 +160,1149 @@ mod tests {
   }
 
   #[test]
-  fn test_save_and_load_params() {
-    save_params(1.5, 2.5).unwrap();
-    let (theta0, theta1) = load_params().unwrap();
-    assert_eq!(theta0, 1.5);
-    assert_eq!(theta1, 2.5);
+  fn test_read_legacy_params() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_theta_{}.txt", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    std::fs::write(&path, "1.5,2.5,f64\n")?;
+    let loaded = read_model::<f64>(&path, Some(&Dataset::load()?))?;
+    assert_eq!(loaded.model.to_model().get_params(), (1.5, 2.5));
+    // Without the dataset, the scale the parameters apply to is unknown
+    assert!(matches!(read_model::<f64>(&path, None), Err(LinearRegressionError::CorruptParameterFile { .. })));
+    assert_eq!(read_model_precision(&path)?, Precision::F64);
+    assert!(matches!(read_model::<f32>(&path, None), Err(LinearRegressionError::VersionMismatch { .. })));
+
+    // All-zero parameters are what older versions wrote before training
+    std::fs::write(&path, "0,0,f64\n")?;
+    let untrained = read_model::<f64>(&path, None)?.model;
+    assert!(!untrained.trained);
+    assert!(matches!(untrained.to_model().predict(42000.0), Err(LinearRegressionError::Untrained)));
+
+    // Files saved with their scaling can predict without the dataset
+    let mut trained: LinearRegression = fixture_model(0.5)?;
+    trained.train(1000)?;
+    let (theta0, theta1) = trained.get_params();
+    std::fs::write(&path, format!("{},{},f64\n{}\n", theta0, theta1, serde_json::to_string(trained.get_normalization())?))?;
+    let loaded = read_model::<f64>(&path, None)?;
+    assert_eq!(loaded.warnings.len(), 1);
+    assert!((loaded.model.to_model().predict(42000.0)?.value - trained.predict(42000.0)?.value).abs() < 1e-6);
+
+    std::fs::write(&path, "1.5,oops,f64\n")?;
+    let corrupt = read_model::<f64>(&path, None);
+    std::fs::remove_file(&path)?;
+    assert!(matches!(corrupt, Err(LinearRegressionError::CorruptParameterFile { .. })));
+    Ok(())
+  }
 
-    let path = std::env::var("THETA_PATH").unwrap();
-    std::fs::remove_file(path).unwrap();
+  #[test]
+  fn test_gradient_check_loss_combinations() -> Result<(), Box<dyn std::error::Error>> {
+    let mut model: LinearRegression = fixture_model(0.001)?;
+    model.set_params(0.3, 0.45);
+
+    let losses = [Loss::Squared, Loss::Absolute, Loss::Huber { delta: 0.1 }];
+    let regularizations = [
+      Regularization::None,
+      Regularization::L1 { lambda: 0.1 },
+      Regularization::L2 { lambda: 0.1 },
+      Regularization::ElasticNet { lambda: 0.1, l1_ratio: 0.5 },
+    ];
+
+    for loss in losses {
+      for regularization in regularizations {
+        model.set_loss(loss);
+        model.set_regularization(regularization);
+        let check = model.check_gradient(1e-6);
+        assert!(check.passed(1e-6), "{:?} + {:?}: {:?}", loss, regularization, check);
+      }
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn test_gradient_check_detects_wrong_gradient() {
+    let cost = |theta0: f64, theta1: f64| theta0 * theta0 + 3.0 * theta1 * theta1;
+    let check = check_gradient(cost, |theta0, theta1| (2.0 * theta0, 3.0 * theta1), (1.0, 1.0), 1e-6);
+
+    assert!(!check.passed(1e-3));
+    assert!((check.numeric.1 - 6.0).abs() < 1e-6);
+  }
+
+  fn synthetic_columns(rows: usize) -> (Vec<(f64, f64)>, Columns<f64>) {
+    let data: Vec<(f64, f64)> = (0..rows)
+      .map(|i| {
+        let x = i as f64 / rows as f64;
+        (x, 1.0 - x + (i % 7) as f64 * 0.01)
+      })
+      .collect();
+    let columns = Columns::from_pairs(&data);
+    (data, columns)
+  }
+
+  #[test]
+  fn test_columns_kernels_match_row_wise_sums() {
+    let (data, columns) = synthetic_columns(1003);
+    let loss = Loss::Huber { delta: 0.05 };
+
+    let loss_sum: f64 = data.iter().map(|&(x, y)| loss.value(0.2 + 0.3 * x - y)).sum();
+    let gradient_sum = data.iter().fold((0.0, 0.0), |(g0, g1), &(x, y)| {
+      let error: f64 = loss.derivative(0.2 + 0.3 * x - y);
+      (g0 + error, g1 + error * x)
+    });
+
+    let (g0, g1) = columns.gradient_sum(0.2, 0.3, loss);
+    assert!((columns.loss_sum(0.2, 0.3, loss) - loss_sum).abs() < 1e-9);
+    assert!((g0 - gradient_sum.0).abs() < 1e-9);
+    assert!((g1 - gradient_sum.1).abs() < 1e-9);
+    assert_eq!(columns.to_pairs(), data);
+  }
+
+  #[cfg(feature = "parallel")]
+  #[test]
+  fn test_parallel_kernels_match_sequential() {
+    let (_, columns) = synthetic_columns(100_003);
+
+    for loss in [Loss::Squared, Loss::Absolute, Loss::Huber { delta: 0.05 }] {
+      let sequential = columns.loss_sum_sequential(0.2, 0.3, loss);
+      let parallel = columns.loss_sum_parallel(0.2, 0.3, loss);
+      assert!((sequential - parallel).abs() <= 1e-9 * sequential.abs());
+
+      let (s0, s1) = columns.gradient_sum_sequential(0.2, 0.3, loss);
+      let (p0, p1) = columns.gradient_sum_parallel(0.2, 0.3, loss);
+      assert!((s0 - p0).abs() <= 1e-9 * s0.abs().max(1.0));
+      assert!((s1 - p1).abs() <= 1e-9 * s1.abs().max(1.0));
+    }
+  }
+
+  #[test]
+  fn test_sufficient_stats_match_row_wise_path() -> Result<(), Box<dyn std::error::Error>> {
+    let mut model: LinearRegression = fixture_model(0.5)?;
+    model.partial_fit(&[(80000.0, 9000.0), (5000.0, 21000.0)])?;
+    model.set_params(0.3, -0.7);
+
+    let data = model.get_normalized_columns();
+    let m = data.len() as f64;
+    let row_cost = data.loss_sum(0.3, -0.7, Loss::Squared) / m;
+    let (row_g0, row_g1) = data.gradient_sum(0.3, -0.7, Loss::Squared);
+
+    let (g0, g1) = model.compute_gradient();
+    assert_eq!(model.get_sufficient_stats().n, 7);
+    assert!((model.compute_cost() - row_cost).abs() < 1e-12);
+    assert!((g0 - row_g0 / m).abs() < 1e-12);
+    assert!((g1 - row_g1 / m).abs() < 1e-12);
+
+    // Near the fit the expanded sums of raw f32 mileages cancel (even below
+    // zero), so rows are summed instead
+    let mut raw: LinearRegression<f32> = LinearRegression::builder().learning_rate(1e-10).scaler(Scaler::None).build();
+    raw.set_dataset(&Dataset::load()?)?;
+    raw.set_params(22001.0, -0.2);
+    let data = raw.get_normalized_columns();
+    assert_eq!(raw.compute_cost(), data.loss_sum(22001.0, -0.2, Loss::Squared) / data.len() as f32);
+    Ok(())
+  }
+
+  #[test]
+  fn test_streaming_training_from_csv() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_stream_{}.csv", std::process::id()));
+    let mut csv = String::from("km,price\n");
+    for i in 0..=1000 {
+      let km = i as f64 * 100.0;
+      csv.push_str(&format!("{},{}\n", km, 22000.0 - 0.2 * km));
+    }
+    std::fs::write(&path, csv)?;
+
+    let mut trainer = StreamingTrainer::new(path.to_string_lossy(), 64);
+    let mut model: LinearRegression = LinearRegression::new(Some(0.5));
+    trainer.init(&mut model)?;
+    let steps = trainer.train(&mut model, 2000)?;
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(trainer.rows(), 1001);
+    assert!(steps > 0);
+    assert_eq!(model.get_normalization().x_max, 100000.0);
+    assert!((model.predict(50000.0)?.value - 12000.0).abs() < 50.0);
+    let distribution = model.get_feature_distribution().ok_or("the streamed model has no feature distribution")?;
+    assert_eq!(distribution.count, 1001);
+    assert!((distribution.quantiles[5] - 50000.0).abs() < 200.0);
+    Ok(())
+  }
+
+  #[test]
+  fn test_training_observer_sees_steps_and_can_stop() -> Result<(), Box<dyn std::error::Error>> {
+    let mut model: LinearRegression = fixture_model(0.5)?;
+    let mut steps: Vec<TrainingStep> = Vec::new();
+
+    let ran = model.train_with(100, &mut |step: &TrainingStep| {
+      steps.push(*step);
+      if step.iteration == 9 { TrainingControl::Stop } else { TrainingControl::Continue }
+    })?;
+
+    assert_eq!(ran, 10);
+    assert_eq!(steps.len(), 10);
+    assert_eq!(steps[0].theta, (0.0, 0.0));
+    assert_eq!(steps[0].learning_rate, 0.5);
+    assert!(steps[9].cost < steps[0].cost);
+
+    // Each step starts from the parameters the previous one moved to
+    let (t0, t1) = steps[0].theta;
+    let (g0, g1) = steps[0].gradient;
+    assert_eq!(steps[1].theta, (t0 - 0.5 * g0, t1 - 0.5 * g1));
+    Ok(())
+  }
+
+  #[test]
+  fn test_training_history_records_descent_path() -> Result<(), Box<dyn std::error::Error>> {
+    let mut model: LinearRegression = fixture_model(0.5)?;
+    let ran = model.train_with(50, &mut |_: &TrainingStep| TrainingControl::Continue)?;
+
+    let history = model.get_history();
+    assert_eq!(history.len(), ran);
+    assert_eq!(history.theta1.len(), ran);
+    assert_eq!((history.theta0[0], history.theta1[0]), (0.0, 0.0));
+    assert!(history.learning_rates.iter().all(|&rate| rate == 0.5));
+    assert!(history.gradient_norms[ran - 1] < history.gradient_norms[0]);
+
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_history_{}", std::process::id()));
+    let csv_path = path.with_extension("csv");
+    let json_path = path.with_extension("json");
+    history.export(&csv_path)?;
+    history.export(&json_path)?;
+
+    let csv = std::fs::read_to_string(&csv_path)?;
+    let json: Vec<serde_json::Value> = serde_json::from_str(&std::fs::read_to_string(&json_path)?)?;
+    std::fs::remove_file(&csv_path)?;
+    std::fs::remove_file(&json_path)?;
+
+    assert_eq!(csv.lines().next(), Some("iteration,cost,theta0,theta1,gradient_norm,learning_rate"));
+    assert_eq!(csv.lines().count(), ran + 1);
+    assert_eq!(json.len(), ran);
+    assert_eq!(json[1]["theta1"].as_f64(), Some(history.theta1[1]));
+    Ok(())
+  }
+
+  #[test]
+  fn test_builder_fit_on_in_memory_dataset() -> Result<(), Box<dyn std::error::Error>> {
+    let dataset: Dataset = (0..=20).map(|i| (i as f64 * 5000.0, 21000.0 - 0.15 * i as f64 * 5000.0)).collect::<Vec<_>>().into();
+    let mut model: LinearRegression = LinearRegression::builder()
+      .learning_rate(0.5)
+      .scaler(Scaler::Standard)
+      .stopping(StoppingCriteria { max_iterations: 5000, tolerance: 1e-12, ..Default::default() })
+      .build();
+
+    let ran = model.fit(&dataset)?;
+    assert!(ran > 0 && ran <= 5000);
+    assert_eq!(model.get_normalization().scaler, Scaler::Standard);
+    assert!((model.predict(60000.0)?.value - 12000.0).abs() < 1.0);
+
+    // Refitting starts over rather than continuing from the previous run
+    let refit = model.fit(&dataset)?;
+    assert_eq!(refit, ran);
+    assert_eq!(model.get_history().len(), ran);
+
+    assert!(model.fit(&Dataset::default()).is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_optimizers_converge() -> Result<(), Box<dyn std::error::Error>> {
+    let dataset: Dataset = Dataset::load()?;
+    let optimizers = [
+      (Optimizer::GradientDescent, 0.5),
+      (Optimizer::Momentum { beta: 0.9 }, 0.1),
+      (Optimizer::adam(), 0.05),
+    ];
+
+    for (optimizer, learning_rate) in optimizers {
+      let mut model: LinearRegression = LinearRegression::builder()
+        .learning_rate(learning_rate)
+        .optimizer(optimizer)
+        .stopping(StoppingCriteria { max_iterations: 3000, tolerance: 0.0, min_gradient_norm: 1e-9 })
+        .build();
+      model.fit(&dataset)?;
+      assert!((model.predict(30000.0)?.value - 16000.0).abs() < 100.0, "{:?}: {}", optimizer, model.predict(30000.0)?.value);
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn test_min_gradient_norm_stops_training() -> Result<(), Box<dyn std::error::Error>> {
+    let mut model: LinearRegression = LinearRegression::builder()
+      .learning_rate(0.5)
+      .stopping(StoppingCriteria { max_iterations: 1000, tolerance: 0.0, min_gradient_norm: 1e-3 })
+      .build();
+
+    let ran = model.fit(&Dataset::load()?)?;
+    assert!(ran < 1000);
+    let (g0, g1) = model.compute_gradient();
+    assert!(g0.hypot(g1) < 1e-3);
+    Ok(())
+  }
+
+  #[test]
+  fn test_errors_are_typed() -> Result<(), Box<dyn std::error::Error>> {
+    let missing = load_dataset_from::<f64>("no/such/dataset.csv");
+    assert!(matches!(missing, Err(LinearRegressionError::DatasetNotFound { .. })));
+
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_errors_{}.csv", std::process::id()));
+    std::fs::write(&path, "km,price\n10000,20000\n20000,abc\n")?;
+    let parsed = load_dataset_from::<f64>(&path.to_string_lossy());
+    std::fs::remove_file(&path)?;
+    assert!(matches!(parsed, Err(LinearRegressionError::Parse { row: 3, column: Some(2), .. })), "{:?}", parsed);
+
+    std::fs::write(&path, b"km,pr\xffice\n10000,20000\n")?;
+    let header = load_dataset_from::<f64>(&path.to_string_lossy());
+    std::fs::remove_file(&path)?;
+    assert!(matches!(header, Err(LinearRegressionError::Parse { row: 1, column: Some(2), .. })), "{:?}", header);
+
+    let model_path = path.with_extension("json").to_string_lossy().to_string();
+    std::fs::write(&model_path, concat!("{\n", "  \"format_version\": 1,\n", "  oops\n", "}\n"))?;
+    let corrupt = ModelFile::<f64>::load(&model_path);
+    std::fs::remove_file(&model_path)?;
+    assert!(
+      matches!(&corrupt, Err(LinearRegressionError::CorruptParameterFile { reason, .. }) if reason.contains("line 3")),
+      "{:?}",
+      corrupt
+    );
+
+    let mut model: LinearRegression = LinearRegression::new(Some(0.5));
+    assert!(matches!(model.fit(&Dataset::default()), Err(LinearRegressionError::EmptyDataset)));
+    assert!(matches!(model.train(10), Err(LinearRegressionError::EmptyDataset)));
+    assert!(matches!(
+      model.fit(&vec![(5000.0, 1.0), (5000.0, 2.0)].into()),
+      Err(LinearRegressionError::DegenerateFeatureRange { .. })
+    ));
+
+    let mut diverging: LinearRegression = fixture_model(1e6)?;
+    let result = diverging.train(1000);
+    assert!(matches!(result, Err(LinearRegressionError::NonFiniteCost { .. })), "{:?}", result);
+    Ok(())
+  }
+
+  #[test]
+  fn test_dataset_source_is_strict_by_default() -> Result<(), Box<dyn std::error::Error>> {
+    let loaded: Dataset = Dataset::load()?;
+    assert!(matches!(loaded.source(), DatasetSource::File(path) if path.ends_with("data.csv")));
+
+    let strict = Dataset::<f64>::load_from("no/such/dataset.csv", Fallback::Strict, &LoadOptions::default());
+    assert!(matches!(strict, Err(LinearRegressionError::DatasetNotFound { .. })));
+
+    let sample: Dataset = Dataset::load_from("no/such/dataset.csv", Fallback::EmbeddedSample, &LoadOptions::default())?;
+    assert_eq!(sample.source(), &DatasetSource::EmbeddedSample);
+    assert_eq!(sample.len(), 24);
+
+    assert_eq!(Dataset::new(vec![(1.0, 2.0)]).source(), &DatasetSource::InMemory);
+    assert_eq!("sample".parse::<Fallback>()?, Fallback::EmbeddedSample);
+    Ok(())
+  }
+
+  #[test]
+  fn test_dataset_columns_provenance_and_describe() -> Result<(), Box<dyn std::error::Error>> {
+    let dataset: Dataset = Dataset::load()?;
+    assert_eq!(dataset.feature(), &Column::km());
+    assert_eq!(dataset.target().name, "price");
+    assert_eq!(dataset.line(0), Some(2));
+    assert_eq!(dataset.line(4), Some(6));
+
+    let description = dataset.describe();
+    assert_eq!(description.feature.count, 5);
+    assert_eq!(description.feature.mean, 30000.0);
+    assert!((description.feature.std - 15811.388).abs() < 1e-3);
+    assert_eq!((description.feature.q1, description.feature.median, description.feature.q3), (20000.0, 30000.0, 40000.0));
+    assert_eq!((description.target.min, description.target.max), (12000.0, 20000.0));
+    assert!((description.correlation + 1.0).abs() < 1e-12);
+    assert!(description.to_string().starts_with(&format!("{:<8}{:>16}{:>16}", "", "km", "price")));
+
+    assert_eq!(Column::from_header("price (EUR)"), Column::new("price", Some("EUR")));
+    assert_eq!(Column::from_header(" mileage [km] ").to_string(), "mileage (km)");
+    assert_eq!(Column::from_header("km"), Column::km());
+    Ok(())
+  }
+
+  #[test]
+  fn test_configurable_csv_parsing() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_options_{}.csv", std::process::id()));
+    let contents = [
+      "id;price (EUR);mileage [km]",
+      "# exported listings",
+      "1;€7.990;22.899",
+      "2;n/a;48.235",
+      "3;'6.900';'76.025'",
+      "4;5.250",
+      "5;3.650 €;240.000",
+    ];
+    std::fs::write(&path, contents.join("\n"))?;
+    let path = path.to_string_lossy().to_string();
+
+    let mut options = LoadOptions {
+      feature: "mileage [km]".parse()?,
+      target: ColumnSelector::Index(1),
+      delimiter: b';',
+      quote: b'\'',
+      comment: Some(b'#'),
+      thousands_separator: Some('.'),
+      ..LoadOptions::default()
+    };
+
+    let failed = Dataset::<f64>::from_file_with(&path, &options);
+    assert!(matches!(failed, Err(LinearRegressionError::Parse { row: 4, column: Some(2), .. })), "{:?}", failed);
+
+    options.bad_rows = BadRowPolicy::Skip;
+    let dataset: Dataset = Dataset::from_file_with(&path, &options)?;
+
+    assert_eq!(dataset.records(), &[(22899.0, 7990.0), (76025.0, 6900.0), (240000.0, 3650.0)]);
+    assert_eq!(dataset.feature(), &Column::new("mileage", Some("km")));
+    assert_eq!(dataset.target(), &Column::new("price", Some("EUR")));
+    assert_eq!(dataset.line(1), Some(5));
+
+    let report = dataset.load_report();
+    assert_eq!(report.rows, 3);
+    assert_eq!(report.dropped.iter().map(|row| row.line).collect::<Vec<_>>(), vec![4, 6]);
+    assert_eq!(report.dropped[1].column, Some(3));
+    assert_eq!(report.dropped[1].reason, "missing value");
+
+    options.feature = ColumnSelector::Name("km".to_string());
+    let unknown = Dataset::<f64>::from_file_with(&path, &options);
+    std::fs::remove_file(&path)?;
+    assert!(matches!(unknown, Err(LinearRegressionError::Parse { row: 1, column: None, .. })));
+    Ok(())
+  }
+
+  #[test]
+  fn test_dataset_formats_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let dataset: Dataset = Dataset::new(vec![(22899.0, 7990.0), (48235.5, 6900.0), (240000.0, 3650.0)])
+      .with_columns(Column::new("mileage", Some("km")), Column::new("price", Some("EUR")));
+    let base = std::env::temp_dir().join(format!("ft_linear_regression_formats_{}", std::process::id()));
+
+    for extension in ["csv", "tsv", "json", "ndjson"] {
+      let path = base.with_extension(extension).to_string_lossy().to_string();
+      dataset.save(&path)?;
+      let loaded: Dataset = Dataset::from_file(&path)?;
+      std::fs::remove_file(&path)?;
+
+      assert_eq!(DatasetFormat::from_path(&path).to_string(), extension);
+      assert_eq!(loaded.records(), dataset.records(), "{}", extension);
+      assert_eq!((loaded.feature(), loaded.target()), (dataset.feature(), dataset.target()), "{}", extension);
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn test_ndjson_field_mapping() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_listings_{}.jsonl", std::process::id()));
+    let lines = [
+      r#"{"id": 1, "odometer": 22899, "asking_price": "$7,990"}"#,
+      r#"{"id": 2, "odometer": null, "asking_price": 6900}"#,
+      "",
+      r#"{"id": 3, "odometer": 240000, "asking_price": 3650}"#,
+      r#"{"id": 4, "odometer": 10"#,
+    ];
+    std::fs::write(&path, lines.join("\n"))?;
+
+    let options = LoadOptions {
+      feature: "odometer".parse()?,
+      target: "asking_price".parse()?,
+      thousands_separator: Some(','),
+      bad_rows: BadRowPolicy::Skip,
+      ..LoadOptions::default()
+    };
+    let dataset: Dataset = Dataset::from_file_with(&path.to_string_lossy(), &options)?;
+
+    let by_index = LoadOptions { feature: ColumnSelector::Index(1), target: ColumnSelector::Index(2), ..options.clone() };
+    let indexed: Dataset = Dataset::from_file_with(&path.to_string_lossy(), &by_index)?;
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(dataset.records(), &[(22899.0, 7990.0), (240000.0, 3650.0)]);
+    assert_eq!(dataset.feature().name, "odometer");
+    assert_eq!(dataset.line(1), Some(4));
+    assert_eq!(dataset.load_report().dropped.iter().map(|row| row.line).collect::<Vec<_>>(), vec![2, 5]);
+    assert_eq!(indexed.records(), dataset.records());
+    assert_eq!(indexed.target().name, "asking_price");
+    Ok(())
+  }
+
+  #[test]
+  fn test_validation_rules() -> Result<(), Box<dyn std::error::Error>> {
+    let dataset: Dataset = Dataset::new(vec![
+      (10000.0, 8000.0),
+      (20000.0, 7000.0),
+      (-5.0, 6500.0),
+      (30000.0, 0.0),
+      (20000.0, 7000.0),
+      (f64::NAN, 6000.0),
+      (40000.0, 6000.0),
+      (50000.0, 5000.0),
+      (1000000.0, 4000.0),
+    ]);
+
+    let (cleaned, report) = dataset.clean(&ValidationRules::default());
+    let issues: Vec<(usize, Issue, Action)> = report.findings.iter().map(|f| (f.row, f.issue, f.action)).collect();
+    assert_eq!(issues, vec![
+      (2, Issue::NegativeFeature(-5.0), Action::Drop),
+      (3, Issue::NonPositiveTarget(0.0), Action::Drop),
+      (4, Issue::Duplicate(1), Action::Flag),
+      (5, Issue::NonFinite(Side::Feature), Action::Drop),
+    ]);
+    assert_eq!((report.rows_kept, report.rows_dropped), (6, 3));
+    assert_eq!(cleaned.records()[2], (20000.0, 7000.0));
+    assert_eq!(cleaned.load_report().rows, 6);
+
+    let rules = ValidationRules {
+      duplicates: Action::Drop,
+      outliers: Some("iqr".parse()?),
+      outlier_action: Action::Drop,
+      ..ValidationRules::default()
+    };
+    let (cleaned, report) = dataset.clean(&rules);
+    assert_eq!(cleaned.len(), 4);
+    assert!(report.findings.iter().any(|f| f.row == 8
+      && f.issue == Issue::Outlier { side: Side::Feature, value: 1000000.0, method: OutlierMethod::Iqr { k: 1.5 } }));
+    assert!(report.to_string().contains("row 8: km 1000000 is an outlier (IQR, k = 1.5), dropped"));
+    assert_eq!("zscore:2".parse::<OutlierMethod>()?, OutlierMethod::ZScore { threshold: 2.0 });
+
+    let constant: Dataset = Dataset::new(vec![(10000.0, 5000.0), (20000.0, 5000.0)]);
+    assert_eq!(constant.clean(&ValidationRules::default()).1.constant_columns, vec!["price".to_string()]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_missing_value_imputation() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_missing_{}.csv", std::process::id()));
+    std::fs::write(&path, "km,price\n10000,9000\n,8000\n30000,7000\n40000,\n50000,5000\n,\n")?;
+    let path = path.to_string_lossy().to_string();
+    let load = |missing: MissingValues| {
+      Dataset::<f64>::from_file_with(&path, &LoadOptions { missing, ..LoadOptions::default() })
+    };
+
+    let failed = load(MissingValues::Fail);
+    let dropped = load(MissingValues::Drop)?;
+    let mean = load(MissingValues::Mean)?;
+    let median = load(MissingValues::Median)?;
+    let regression = load(MissingValues::Regression)?;
+    std::fs::remove_file(&path)?;
+
+    assert!(matches!(failed, Err(LinearRegressionError::Parse { row: 3, column: Some(1), .. })));
+    assert_eq!(dropped.records(), &[(10000.0, 9000.0), (30000.0, 7000.0), (50000.0, 5000.0)]);
+    assert_eq!(dropped.load_report().dropped.iter().map(|row| row.line).collect::<Vec<_>>(), vec![3, 5, 7]);
+    assert!(dropped.imputation().is_none());
+
+    assert_eq!(mean.records()[1], (32500.0, 8000.0));
+    assert_eq!(mean.records()[3], (40000.0, 7250.0));
+    assert_eq!(median.records()[1], (35000.0, 8000.0));
+    assert_eq!(mean.load_report().dropped.len(), 1);
+    assert_eq!(mean.load_report().imputed.iter().map(|value| (value.line, value.column.as_str())).collect::<Vec<_>>(),
+      vec![(3, "km"), (5, "price")]);
+    let filtered = mean.filter_rows(|row| row != 1);
+    assert_eq!(filtered.load_report().rows, 4);
+    assert_eq!(filtered.load_report().imputed.iter().map(|value| value.line).collect::<Vec<_>>(), vec![5]);
+
+    // Complete rows lie on price = 10000 - 0.1 km
+    assert_eq!(regression.records()[1], (20000.0, 8000.0));
+    assert_eq!(regression.records()[3], (40000.0, 6000.0));
+
+    let mut model = LinearRegression::new(Some(0.5));
+    model.set_dataset(&mean)?;
+    let imputation: Imputer = *model.get_imputation().ok_or("imputation not recorded")?;
+    assert_eq!(imputation.strategy, MissingValues::Mean);
+    assert_eq!(imputation.feature::<f64>(None), 32500.0);
+    let replayed: Imputer = serde_json::from_str(&serde_json::to_string(&imputation)?)?;
+    assert_eq!(replayed, imputation);
+    Ok(())
+  }
+
+  #[test]
+  fn test_model_file_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let dataset: Dataset = Dataset::load()?;
+    let mut trained: LinearRegression = LinearRegression::builder()
+      .learning_rate(0.5)
+      .optimizer(Optimizer::Momentum { beta: 0.5 })
+      .scaler(Scaler::Standard)
+      .stopping(StoppingCriteria { max_iterations: 3000, tolerance: 0.0, min_gradient_norm: 1e-9 })
+      .build();
+    let steps = trained.fit(&dataset)?;
+
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_model_{}.json", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    let mut saved = ModelFile::from_model(&trained);
+    saved.save(&path)?;
+    let loaded: ModelFile = ModelFile::load(&path)?;
+    assert_eq!(loaded, saved);
+    let mut resaved = loaded.clone();
+    resaved.save(&path)?;
+    assert_eq!(resaved.created_at, saved.created_at);
+    assert!(resaved.updated_at > saved.updated_at);
+    assert_eq!(model_precision(&path)?, Precision::F64);
+    assert!(matches!(ModelFile::<f32>::load(&path), Err(LinearRegressionError::VersionMismatch { .. })));
+
+    // Everything needed to predict is in the file
+    let model = loaded.to_model();
+    assert_eq!(model.predict(42000.0)?.value, trained.predict(42000.0)?.value);
+    assert_eq!(model.get_normalization().scaler, Scaler::Standard);
+    assert_eq!(model.get_optimizer(), Optimizer::Momentum { beta: 0.5 });
+    assert_eq!(loaded.training.iterations, steps);
+    assert_eq!(loaded.dataset, Some(dataset.fingerprint()));
+    assert_eq!(loaded.dataset.as_ref().map(|fingerprint| fingerprint.rows), Some(5));
+    assert!(loaded.metrics.ok_or("metrics not saved")?.r2 > 0.99);
+
+    let mut newer: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    newer["format_version"] = (MODEL_FORMAT_VERSION + 1).into();
+    std::fs::write(&path, newer.to_string())?;
+    let rejected = ModelFile::<f64>::load(&path);
+    std::fs::remove_file(&path)?;
+    std::fs::remove_file(backup_path(&path))?;
+    assert!(matches!(rejected, Err(LinearRegressionError::VersionMismatch { .. })));
+    assert!(matches!(ModelFile::<f64>::load(&path), Err(LinearRegressionError::ModelNotFound { .. })));
+    Ok(())
+  }
+
+  #[test]
+  fn test_legacy_theta_migration() -> Result<(), Box<dyn std::error::Error>> {
+    let base = std::env::temp_dir().join(format!("ft_linear_regression_legacy_{}", std::process::id()));
+    let (legacy, migrated) = (base.with_extension("txt"), base.with_extension("json"));
+    let (legacy, migrated) = (legacy.to_string_lossy().to_string(), migrated.to_string_lossy().to_string());
+    std::fs::write(&legacy, "0.9,-0.8\n")?;
+    let dataset: Dataset = Dataset::load()?;
+
+    let without = read_model::<f64>(&legacy, None);
+    let with = read_model(&legacy, Some(&dataset))?;
+    assert_eq!(detect_parameter_format(&legacy)?, ParameterFileFormat::Legacy);
+    assert_eq!(read_model_precision(&legacy)?, Precision::F64);
+    assert!(matches!(without, Err(LinearRegressionError::CorruptParameterFile { .. })));
+    assert!(matches!(migrate_model::<f64>(&legacy, &migrated, None), Err(LinearRegressionError::CorruptParameterFile { .. })));
+    assert!(!std::path::Path::new(&migrated).exists());
+    assert_eq!(with.format, ParameterFileFormat::Legacy);
+    assert!(with.warnings.is_empty());
+    assert_eq!(with.model.dataset, Some(dataset.fingerprint()));
+    assert!(with.model.metrics.is_some());
+
+    // The reconstructed model scales inputs like the dataset it was trained on
+    let mut expected: LinearRegression = LinearRegression::new(None);
+    expected.set_dataset(&dataset)?;
+    expected.set_params(0.9, -0.8);
+    assert_eq!(with.model.to_model().predict(25000.0)?.value, expected.predict(25000.0)?.value);
+
+    let written = migrate_model(&legacy, &migrated, Some(&dataset))?;
+    let reloaded = read_model::<f64>(&migrated, None)?;
+    std::fs::write(&legacy, "")?;
+    let empty = detect_parameter_format(&legacy);
+    std::fs::remove_file(&legacy)?;
+    std::fs::remove_file(&migrated)?;
+
+    assert_eq!(reloaded.format, ParameterFileFormat::Model);
+    assert_eq!(reloaded.model, written.model);
+    assert_eq!(reloaded.model.to_model().predict(25000.0)?.value, expected.predict(25000.0)?.value);
+    assert!(matches!(empty, Err(LinearRegressionError::ModelNotFound { .. })));
+    Ok(())
+  }
+
+  #[test]
+  fn test_atomic_writes_keep_a_backup() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_atomic_{}.txt", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    let write = |contents: &'static str| write_atomically(&path, |file| Ok(std::io::Write::write_all(file, contents.as_bytes())?));
+
+    write("first")?;
+    write("second")?;
+    assert_eq!(read_locked(&path)?, "second");
+    assert_eq!(std::fs::read_to_string(backup_path(&path))?, "first");
+
+    // A failed write leaves the file and its backup untouched
+    let failed = write_atomically(&path, |_| Err(LinearRegressionError::EmptyDataset));
+    assert!(matches!(failed, Err(LinearRegressionError::EmptyDataset)));
+    assert_eq!(read_locked(&path)?, "second");
+    assert_eq!(std::fs::read_to_string(backup_path(&path))?, "first");
+    assert!(!std::path::Path::new(&format!("{}.lock", path)).exists());
+
+    for file in [path.clone(), backup_path(&path)] {
+      std::fs::remove_file(file)?;
+    }
+    let parent = std::path::Path::new(&path).parent().ok_or("no parent")?;
+    let leftovers = std::fs::read_dir(parent)?
+      .filter_map(|entry| entry.ok())
+      .any(|entry| entry.file_name().to_string_lossy().contains(&format!("atomic_{}", std::process::id())));
+    assert!(!leftovers, "temporary file left behind");
+
+    // Reading never creates the file
+    assert!(read_locked(&path).is_err());
+    assert!(matches!(read_model::<f64>(&path, None), Err(LinearRegressionError::ModelNotFound { .. })));
+    assert!(!std::path::Path::new(&path).exists());
+    Ok(())
+  }
+
+  #[test]
+  fn test_model_registry() -> Result<(), Box<dyn std::error::Error>> {
+    let root = std::env::temp_dir().join(format!("ft_linear_regression_registry_{}", std::process::id()));
+    let registry = Registry::open(&root.to_string_lossy())?;
+    let mut first: LinearRegression = fixture_model(0.5)?;
+    first.train(10)?;
+    let mut second: LinearRegression = fixture_model(0.5)?;
+    second.train(1000)?;
+    let a = registry.register(&ModelFile::from_model(&first))?;
+    let b = registry.register(&ModelFile::from_model(&second))?;
+
+    let ids: Vec<String> = registry.entries()?.into_iter().map(|entry| entry.id).collect();
+    assert_eq!(ids, [a.id.clone(), b.id.clone()]);
+    assert_eq!(registry.resolve(LATEST)?.id, b.id);
+    assert_eq!(registry.resolve(&a.id[..a.id.len() - 2])?.id, a.id);
+    assert!(b.metrics.ok_or("metrics not saved")?.r2 > a.metrics.ok_or("metrics not saved")?.r2);
+
+    registry.promote(&a.id)?;
+    registry.tag("staging", &b.id)?;
+    assert_eq!(registry.tags_of(&b.id)?, ["staging"]);
+    assert!((registry.load::<f64>("staging")?.to_model().predict(30000.0)?.value - second.predict(30000.0)?.value).abs() < 1e-6);
+    assert!(matches!(registry.load::<f32>("staging"), Err(LinearRegressionError::VersionMismatch { .. })));
+
+    // Promoting replaces production, and rolling back restores it
+    registry.promote("staging")?;
+    assert_eq!(registry.resolve(PRODUCTION_TAG)?.id, b.id);
+    assert_eq!(registry.rollback(PRODUCTION_TAG)?.id, a.id);
+    assert_eq!(registry.resolve(PRODUCTION_TAG)?.id, a.id);
+    assert!(matches!(registry.rollback(PRODUCTION_TAG), Err(LinearRegressionError::Registry { .. })));
+    assert!(matches!(registry.resolve("missing"), Err(LinearRegressionError::Registry { .. })));
+    assert!(matches!(registry.tag(LATEST, &a.id), Err(LinearRegressionError::Registry { .. })));
+
+    let diff = registry.diff(PRODUCTION_TAG, "staging")?;
+    let changed: Vec<&str> = diff.changes.iter().map(|change| change.field.as_str()).collect();
+    assert!(changed.contains(&"theta0") && changed.contains(&"training.iterations"));
+    assert!(!changed.contains(&"normalization.x_min"));
+    assert!(registry.diff(&a.id, &a.id)?.changes.is_empty());
+
+    // A registered model keeps its predictions once attached to the data again
+    let mut attached = registry.load::<f64>(PRODUCTION_TAG)?.to_model();
+    attached.set_normalization(Default::default());
+    attached.set_params(1.0, -1.0);
+    let before = attached.predict(30000.0)?.value;
+    attached.attach_dataset(&Dataset::load()?)?;
+    assert!((attached.predict(30000.0)?.value - before).abs() < 1e-6);
+
+    // An f32 model is widened to f64 and attached, as the GUI loads it
+    let mut single: LinearRegression<f32> = fixture_model(0.5)?;
+    single.train(1000)?;
+    let c = registry.register(&ModelFile::from_model(&single))?;
+    assert!(matches!(registry.load::<f64>(&c.id), Err(LinearRegressionError::VersionMismatch { .. })));
+    let mut widened = registry.load_as::<f64>(&c.id)?.to_model();
+    widened.attach_dataset(&Dataset::load()?.clean(&ValidationRules::default()).0)?;
+    assert!((widened.predict(30000.0)?.value - single.predict(30000.0)?.value as f64).abs() < 1e-2);
+    assert_eq!(registry.load_as::<f64>(&b.id)?, registry.load::<f64>(&b.id)?);
+    std::fs::remove_dir_all(&root)?;
+    Ok(())
+  }
+
+  /// Evaluates an arithmetic expression of numbers, `+ - * /` and
+  /// parentheses, with `min` and `max` as functions or methods and SQL's
+  /// `CASE WHEN a < b THEN c ... ELSE d END`
+  fn evaluate(expression: &str) -> Result<f64, String> {
+    type Tokens = std::iter::Peekable<std::vec::IntoIter<String>>;
+    fn expect(tokens: &mut Tokens, expected: &str) -> Result<(), String> {
+      tokens.next_if(|token| token == expected).map(|_| ()).ok_or(format!("expected '{}'", expected))
+    }
+    fn sum(tokens: &mut Tokens) -> Result<f64, String> {
+      let mut value = product(tokens)?;
+      while let Some(op) = tokens.next_if(|token| token == "+" || token == "-") {
+        let rhs = product(tokens)?;
+        value = if op == "+" { value + rhs } else { value - rhs };
+      }
+      Ok(value)
+    }
+    fn product(tokens: &mut Tokens) -> Result<f64, String> {
+      let mut value = atom(tokens)?;
+      while let Some(op) = tokens.next_if(|token| token == "*" || token == "/") {
+        let rhs = atom(tokens)?;
+        value = if op == "*" { value * rhs } else { value / rhs };
+      }
+      Ok(value)
+    }
+    fn bound(function: &str, value: f64, limit: f64) -> f64 {
+      if function.ends_with("max") { value.max(limit) } else { value.min(limit) }
+    }
+    fn case(tokens: &mut Tokens) -> Result<f64, String> {
+      let mut chosen = None;
+      while tokens.next_if(|token| token == "WHEN").is_some() {
+        let lhs = sum(tokens)?;
+        let op = tokens.next().ok_or("unexpected end")?;
+        let rhs = sum(tokens)?;
+        expect(tokens, "THEN")?;
+        let then = sum(tokens)?;
+        let holds = match op.as_str() {
+          "<" => lhs < rhs,
+          ">" => lhs > rhs,
+          op => return Err(format!("unexpected '{}'", op)),
+        };
+        chosen = chosen.or(holds.then_some(then));
+      }
+      expect(tokens, "ELSE")?;
+      let otherwise = sum(tokens)?;
+      expect(tokens, "END")?;
+      Ok(chosen.unwrap_or(otherwise))
+    }
+    fn atom(tokens: &mut Tokens) -> Result<f64, String> {
+      let mut value = match tokens.next().as_deref() {
+        Some("(") => {
+          let value = sum(tokens)?;
+          expect(tokens, ")")?;
+          value
+        }
+        Some("-") => -atom(tokens)?,
+        Some("CASE") => case(tokens)?,
+        Some(function @ ("max" | "min")) => {
+          expect(tokens, "(")?;
+          let value = sum(tokens)?;
+          expect(tokens, ",")?;
+          let limit = sum(tokens)?;
+          expect(tokens, ")")?;
+          bound(function, value, limit)
+        }
+        Some(number) => number.parse().map_err(|_| format!("unexpected '{}'", number))?,
+        None => return Err("unexpected end".to_string()),
+      };
+      while let Some(method) = tokens.next_if(|token| token == ".max" || token == ".min") {
+        expect(tokens, "(")?;
+        let limit = sum(tokens)?;
+        expect(tokens, ")")?;
+        value = bound(&method, value, limit);
+      }
+      Ok(value)
+    }
+
+    let spaced: String = expression.chars().flat_map(|c| match c {
+      '(' | ')' | '+' | '-' | '*' | '/' | ',' | '<' | '>' => vec![' ', c, ' '],
+      c => vec![c],
+    }).collect();
+    let mut tokens = spaced.split_whitespace().map(str::to_string).collect::<Vec<_>>().into_iter().peekable();
+    let value = sum(&mut tokens)?;
+    match tokens.next() {
+      Some(token) => Err(format!("unexpected '{}'", token)),
+      None => Ok(value),
+    }
+  }
+
+  /// The expression `export` wrote for `language`, with `mileage` substituted
+  /// and spelled so `evaluate` reads it
+  fn exported_expression(language: Language, code: &str, mileage: f64) -> Result<String, String> {
+    let expression = match language {
+      Language::Rust => code.lines().nth(2),
+      Language::C | Language::Python | Language::JavaScript => {
+        code.lines().find_map(|line| line.trim().strip_prefix("return ")).map(|line| line.trim_end_matches(';'))
+      }
+      Language::Sql => code.lines().nth(1).and_then(|line| line.strip_suffix(" AS price")),
+      Language::Latex => code.split_once(" = ").map(|(_, expression)| expression),
+    };
+    let expression = expression.ok_or(format!("no expression in the {} export:\n{}", language, code))?;
+    Ok(expression
+      .replace("\\mathrm{mileage}", "mileage")
+      .replace("\\max\\left(", "max(")
+      .replace("\\min\\left(", "min(")
+      .replace("\\left(", "(")
+      .replace("\\right)", ")")
+      .replace("\\cdot", "*")
+      .replace("Math.", "")
+      .replace("fmax(", "max(")
+      .replace("fmin(", "min(")
+      .replace("mileage", &format!("{:?}", mileage)))
+  }
+
+  #[test]
+  fn test_code_export_matches_predict() -> Result<(), Box<dyn std::error::Error>> {
+    for scaler in [Scaler::MinMax, Scaler::Standard, Scaler::None] {
+      let learning_rate = if scaler == Scaler::None { 1e-10 } else { 0.5 };
+      let mut model: LinearRegression = LinearRegression::builder().learning_rate(learning_rate).scaler(scaler).build();
+      model.fit(&Dataset::load()?)?;
+
+      // Bounds a quarter of the way in from the predictions at 10000 and
+      // 50000 km, so those two are clamped and 30000 km is not
+      let ends = (model.predict_unchecked(10000.0), model.predict_unchecked(50000.0));
+      let (low, high) = (ends.0.min(ends.1), ends.0.max(ends.1));
+      let bounds = ClampPolicy { floor: Some(low + (high - low) / 4.0), ceiling: Some(high - (high - low) / 4.0) };
+      for clamp in [ClampPolicy::NONE, bounds] {
+        model.set_clamp(clamp);
+        for language in Language::ALL {
+          let options = ExportOptions { feature: "mileage".to_string(), ..ExportOptions::default() };
+          let code = export(&model, language, &options)?;
+          let expression = formula(&model, language, "mileage")?;
+          assert!(code.contains(&expression), "{} export lacks its formula:\n{}", language, code);
+          assert_eq!(language.to_string().parse::<Language>()?, language);
+
+          for mileage in [0.0, 10000.0, 30000.0, 42000.0, 50000.0, 250000.0] {
+            let value = evaluate(&exported_expression(language, &code, mileage)?)?;
+            let expected = model.predict(mileage)?.value;
+            assert!(
+              (value - expected).abs() <= 1e-9 * expected.abs().max(1.0),
+              "{} ({:?}, {}): {} != {}", language, scaler, clamp, value, expected
+            );
+          }
+        }
+      }
+      assert!(model.predict(10000.0)?.is_clamped() && model.predict(50000.0)?.is_clamped());
+      assert!(!model.predict(30000.0)?.is_clamped());
+    }
+
+    let code = export(&LinearRegression::<f64>::new(None), Language::JavaScript, &ExportOptions::default())?;
+    assert!(code.contains("function predictPrice(km)"));
+
+    let mut model: LinearRegression = LinearRegression::builder().scaler(Scaler::None).build();
+    model.set_params(8000.0, -0.05);
+    assert_eq!(
+      export(&model, Language::Rust, &ExportOptions::default())?,
+      concat!(
+        "/// Predicts price from km, as fitted by ft_linear_regression.\n",
+        "pub fn predict_price(km: f64) -> f64 {\n",
+        "    8000.0 + (-0.05) * km\n",
+        "}\n",
+      )
+    );
+    model.set_clamp("floor=500,ceiling=7000".parse()?);
+    assert_eq!(
+      export(&model, Language::Rust, &ExportOptions::default())?,
+      concat!(
+        "/// Predicts price from km, as fitted by ft_linear_regression.\n",
+        "pub fn predict_price(km: f64) -> f64 {\n",
+        "    ((8000.0 + (-0.05) * km).max(500.0)).min(7000.0)\n",
+        "}\n",
+      )
+    );
+    // SQLite has no GREATEST or LEAST
+    assert_eq!(
+      export(&model, Language::Sql, &ExportOptions::default())?,
+      concat!(
+        "-- Predicts price from km, as fitted by ft_linear_regression.\n",
+        "CASE WHEN 8000.0 + (-0.05) * km < 500.0 THEN 500.0 ",
+        "WHEN 8000.0 + (-0.05) * km > 7000.0 THEN 7000.0 ",
+        "ELSE 8000.0 + (-0.05) * km END AS price\n",
+      )
+    );
+
+    model.set_params(f64::NAN, -0.05);
+    assert!(matches!(
+      export(&model, Language::C, &ExportOptions::default()),
+      Err(LinearRegressionError::NonFiniteParameter { name }) if name == "theta0"
+    ));
+    Ok(())
+  }
+
+  #[test]
+  fn test_pmml_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_missing_pmml_{}.csv", std::process::id()));
+    std::fs::write(&path, "km,price\n10000,20000\n20000,\n30000,16000\n,14000\n50000,12000\n")?;
+    let options = LoadOptions { missing: MissingValues::Median, ..LoadOptions::default() };
+    let dataset: Dataset = Dataset::from_file_with(&path.to_string_lossy(), &options)?;
+    std::fs::remove_file(&path)?;
+
+    let mut model: LinearRegression = LinearRegression::builder().learning_rate(0.5).scaler(Scaler::Standard).build();
+    model.fit(&dataset)?;
+    let xml = to_pmml(&model, "mileage", "price")?;
+    assert!(xml.contains("<RegressionModel") && xml.contains("<NumericPredictor name=\"mileage\""));
+    assert!(xml.contains("missingValueReplacement=\"25000\""));
+
+    let imported = from_pmml::<f64>(&xml)?;
+    assert_eq!((imported.feature.as_str(), imported.target.as_str()), ("mileage", "price"));
+    assert_eq!(imported.model.get_normalization(), model.get_normalization());
+    assert_eq!(imported.model.get_imputation(), model.get_imputation());
+    for mileage in [0.0, 25000.0, 80000.0] {
+      assert!((imported.model.predict(mileage)?.value - model.predict(mileage)?.value).abs() < 1e-9);
+    }
+
+    let file = std::env::temp_dir().join(format!("ft_linear_regression_{}.pmml", std::process::id()));
+    let file = file.to_string_lossy().to_string();
+    save_pmml(&model, "km", "price", &file)?;
+    let loaded = load_pmml::<f32>(&file)?;
+    std::fs::remove_file(&file)?;
+    assert!((loaded.model.predict(25000.0)?.value - model.predict(25000.0)?.value as f32).abs() < 0.1);
+
+    // Documents from other tools hold coefficients in original units
+    let foreign = r#"<?xml version="1.0"?>
+      <PMML xmlns="http://www.dmg.org/PMML-4_3" version="4.3">
+        <DataDictionary><DataField name="odometer" optype="continuous" dataType="double"/></DataDictionary>
+        <RegressionModel functionName="regression">
+          <MiningSchema><MiningField name="odometer"/><MiningField name="value" usageType="predicted"/></MiningSchema>
+          <RegressionTable intercept="8500.5"><NumericPredictor name="odometer" coefficient="-0.0214"/></RegressionTable>
+        </RegressionModel>
+      </PMML>"#;
+    let imported = from_pmml::<f64>(foreign)?;
+    assert_eq!((imported.feature.as_str(), imported.target.as_str()), ("odometer", "value"));
+    assert_eq!(imported.model.get_normalization().scaler, Scaler::None);
+    assert!((imported.model.predict(100000.0)?.value - (8500.5 - 2140.0)).abs() < 1e-6);
+
+    for unsupported in [
+      foreign.replace("coefficient=", "exponent=\"2\" coefficient="),
+      foreign.replace("</RegressionTable>", "<PredictorTerm coefficient=\"1\"/></RegressionTable>"),
+      foreign.replace("regression\"", "classification\""),
+      foreign.replace("RegressionModel", "TreeModel"),
+      "<PMML><RegressionModel>".to_string(),
+      foreign.replace("8500.5", "NaN"),
+      foreign.replace("-0.0214", "-inf"),
+    ] {
+      assert!(matches!(from_pmml::<f64>(&unsupported), Err(LinearRegressionError::CorruptParameterFile { .. })), "{}", unsupported);
+    }
+
+    // Other tools reject NaN and the infinities, so they are never written
+    let mut broken: LinearRegression = fixture_model(0.5)?;
+    broken.set_params(f64::NAN, -0.5);
+    let err = to_pmml(&broken, "km", "price").unwrap_err();
+    assert!(matches!(&err, LinearRegressionError::NonFiniteParameter { name } if name == "intercept"), "{:?}", err);
+    broken.set_params(1.0, -0.5);
+    broken.set_clamp(ClampPolicy { floor: None, ceiling: Some(f64::INFINITY) });
+    let err = to_pmml(&broken, "km", "price").unwrap_err();
+    assert!(matches!(&err, LinearRegressionError::NonFiniteParameter { name } if name == "clamp ceiling"), "{:?}", err);
+    Ok(())
+  }
+
+  #[test]
+  fn test_dataset_fingerprint_detects_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_fingerprint_{}.csv", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    std::fs::write(&path, "km,price\n10000,20000\n20000,18000\n30000,16000\n")?;
+    let original: Dataset = Dataset::from_file(&path)?;
+
+    // Streaming training hashes the rows as it reads them
+    let mut streamed: LinearRegression = LinearRegression::new(Some(0.5));
+    let mut trainer = StreamingTrainer::new(path.clone(), 2);
+    trainer.init(&mut streamed)?;
+    let fingerprint = streamed.get_dataset_fingerprint().ok_or("no fingerprint")?;
+    assert!(fingerprint.streamed && fingerprint.matches(&original.fingerprint()));
+
+    // A streamed row with a missing cell is dropped rather than imputed,
+    // and checks of a streamed fingerprint drop it too
+    std::fs::write(&path, "km,price\n10000,20000\n20000,\n30000,16000\n")?;
+    let options = LoadOptions { missing: MissingValues::Mean, ..LoadOptions::default() };
+    let mut trainer = StreamingTrainer::new(path.clone(), 2).with_options(options.clone());
+    let mut streamed: LinearRegression = LinearRegression::new(Some(0.5));
+    trainer.init(&mut streamed)?;
+    assert_eq!(trainer.load_report().dropped.len(), 1);
+    let fingerprint = streamed.get_dataset_fingerprint().ok_or("no fingerprint")?;
+    assert_eq!(fingerprint.rows, 2);
+    let loaded: Dataset = Dataset::from_file_with(&path, &options)?;
+    assert!(!fingerprint.matches(&loaded.fingerprint()));
+    assert_eq!(DatasetMatch::of(Some(fingerprint), trainer.fingerprint()?), DatasetMatch::Same);
+
+    std::fs::write(&path, "km,price\n10000,20000\n20000,18500\n30000,16000\n")?;
+    let edited: Dataset = Dataset::from_file(&path)?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(DatasetMatch::of(Some(&original.fingerprint()), original.fingerprint()), DatasetMatch::Same);
+    assert_eq!(DatasetMatch::of(None, edited.fingerprint()), DatasetMatch::Unknown);
+    let changed = DatasetMatch::of(Some(&original.fingerprint()), edited.fingerprint());
+    assert!(matches!(changed, DatasetMatch::Changed { .. }));
+    assert!(changed.warning().ok_or("no warning")?.contains(edited.fingerprint().short_hash()));
+    assert_eq!(edited.fingerprint().rows, original.fingerprint().rows);
+
+    // DATASET_PATH is compared in the precision the model was trained in
+    let model: LinearRegression<f32> = fixture_model(0.5)?;
+    assert_eq!(check_dataset_fingerprint::<f32>(model.get_dataset_fingerprint())?, DatasetMatch::Same);
+    let model: LinearRegression = fixture_model(0.5)?;
+    assert_eq!(check_dataset_fingerprint::<f64>(model.get_dataset_fingerprint())?, DatasetMatch::Same);
+    assert!(check_dataset_fingerprint::<f64>(Some(&original.fingerprint()))?.warning().is_some());
+    Ok(())
+  }
+
+  #[test]
+  fn test_prediction_flags_extrapolation_and_clamps() -> Result<(), Box<dyn std::error::Error>> {
+    let mut model: LinearRegression = fixture_model(0.5)?;
+    assert!(matches!(model.predict(30000.0), Err(LinearRegressionError::Untrained)));
+
+    // Parameters that happen to be zero still belong to a trained model,
+    // and model files without the flag only ever held trained ones
+    model.set_params(0.0, 0.0);
+    assert_eq!(model.predict(30000.0)?.value, model.predict_unchecked(30000.0));
+    let mut json = serde_json::to_value(ModelFile::from_model(&model))?;
+    json.as_object_mut().ok_or("not an object")?.remove("trained");
+    assert!(serde_json::from_value::<ModelFile>(json)?.to_model().is_trained());
+    let mut model: LinearRegression = fixture_model(0.5)?;
+    model.train(1000)?;
+
+    let inside = model.predict(30000.0)?;
+    assert_eq!((inside.extrapolation, inside.is_clamped()), (None, false));
+    assert_eq!(model.predict(5000.0)?.extrapolation, Some(Extrapolation::Below { min: 10000.0 }));
+    let far = model.predict(200000.0)?;
+    assert_eq!(far.extrapolation, Some(Extrapolation::Above { max: 50000.0 }));
+    assert!(far.value < 0.0 && far.value == model.predict_unchecked(200000.0));
+
+    model.set_clamp(ClampPolicy::NON_NEGATIVE);
+    let clamped = model.predict(200000.0)?;
+    assert!(clamped.is_clamped() && clamped.value == 0.0 && clamped.unclamped == far.value);
+    let mut broken: LinearRegression = fixture_model(0.5)?;
+    broken.set_params(f64::NAN, -1.0);
+    assert!(!broken.predict(200000.0)?.is_clamped());
+
+    let policy: ClampPolicy = "floor=500, ceiling=18000".parse()?;
+    assert_eq!(policy, ClampPolicy { floor: Some(500.0), ceiling: Some(18000.0) });
+    assert_eq!(policy.to_string().parse::<ClampPolicy>()?, policy);
+    assert_eq!("non-negative".parse::<ClampPolicy>()?, ClampPolicy::NON_NEGATIVE);
+    assert_eq!("none".parse::<ClampPolicy>()?, ClampPolicy::NONE);
+    for invalid in ["floor=2,ceiling=1", "floor=cheap", "minimum=3", "positive"] {
+      assert!(invalid.parse::<ClampPolicy>().is_err(), "{}", invalid);
+    }
+
+    // The policy travels with the model
+    model.set_clamp(policy);
+    assert_eq!(model.predict(0.0)?.value, 18000.0);
+    let reloaded = ModelFile::from_model(&model).to_model();
+    assert_eq!(reloaded.get_clamp(), policy);
+    assert_eq!(reloaded.get_training_range(), Some((10000.0, 50000.0)));
+    let imported = from_pmml::<f64>(&to_pmml(&model, "km", "price")?)?;
+    assert_eq!(imported.model.get_clamp(), policy);
+    let code = export(&model, Language::Python, &ExportOptions::default())?;
+    assert!(code.contains("return min(max("), "{}", code);
+
+    // Models known only by their coefficients cannot tell when they extrapolate
+    let mut bare: LinearRegression = LinearRegression::builder().scaler(Scaler::None).build();
+    bare.set_params(8000.0, -0.05);
+    assert_eq!(bare.predict(1e6)?.extrapolation, None);
+    Ok(())
+  }
+
+  #[test]
+  fn test_drift_monitor() -> Result<(), Box<dyn std::error::Error>> {
+    let mut model: LinearRegression = fixture_model(0.5)?;
+    model.train(100)?;
+    let distribution = model.get_feature_distribution().ok_or("the model has no feature distribution")?;
+    assert_eq!((distribution.count, distribution.quantiles.len()), (5, DISTRIBUTION_BINS + 1));
+    assert_eq!((distribution.quantiles[0], distribution.quantiles[DISTRIBUTION_BINS]), (10000.0, 50000.0));
+    assert!(distribution.quantiles.windows(2).all(|pair| pair[0] <= pair[1]));
+
+    // The summary travels with the model
+    assert_eq!(ModelFile::from_model(&model).to_model().get_feature_distribution(), Some(distribution));
+    let imported = from_pmml::<f64>(&to_pmml(&model, "km", "price")?)?;
+    assert_eq!(imported.model.get_feature_distribution(), Some(distribution));
+
+    let training: Vec<f64> = (0..1000).map(|i| i as f64 + 0.5).collect();
+    let distribution = FeatureDistribution::of(&training).ok_or("no distribution")?;
+    for (i, quantile) in distribution.quantiles.iter().enumerate() {
+      assert!((quantile - i as f64 * 100.0).abs() < 2.0, "decile {}: {}", i, quantile);
+    }
+    assert!((distribution.cdf(250.0) - 0.25).abs() < 0.01);
+    let halves = FeatureDistribution::of(&training[..400]).ok_or("no distribution")?.merged(&training[400..]);
+    assert_eq!(halves.count, 1000);
+    for (merged, whole) in halves.quantiles.iter().zip(&distribution.quantiles) {
+      assert!((merged - whole).abs() < 2.0, "{} vs {}", merged, whole);
+    }
+    assert_eq!((distribution.cdf(-1.0), distribution.cdf(2000.0)), (0.0, 1.0));
+
+    let monitor = DriftMonitor::new(distribution, DriftThresholds::default());
+    let similar: Vec<f64> = (0..200).map(|i| i as f64 * 5.0 + 2.0).collect();
+    let report = monitor.check(&similar).ok_or("no report")?;
+    assert!(!report.drifted && report.psi < 0.1 && report.ks_p_value > 0.5, "{:?}", report);
+    assert_eq!((report.count, report.out_of_range), (200, 0));
+
+    let shifted: Vec<f64> = similar.iter().map(|km| km + 500.0).collect();
+    let report = monitor.check(&shifted).ok_or("no report")?;
+    assert!(report.drifted && report.psi > 0.25 && report.ks_p_value < 0.001, "{:?}", report);
+    assert!((report.ks_statistic - 0.5).abs() < 0.02);
+    assert_eq!(report.out_of_range, 100);
+    // A NaN in the batch does not leave the rest of it unsorted
+    let mut reversed: Vec<f64> = shifted.iter().rev().copied().collect();
+    reversed.insert(100, f64::NAN);
+    let with_nan = monitor.check(&reversed).ok_or("no report")?;
+    assert!(with_nan.drifted && (with_nan.ks_statistic - report.ks_statistic).abs() < 0.01, "{:?}", with_nan);
+    assert!(report.to_string().contains("Drift detected"));
+    let few = monitor.check(&[100.0, 400.0, 700.0]).ok_or("no report")?;
+    assert!(!few.psi_counted && !few.drifted, "{:?}", few);
+    assert_eq!(monitor.check::<f64>(&[]), None);
+
+    let path = std::env::temp_dir().join(format!("ft_linear_regression_inputs_{}.csv", std::process::id()));
+    std::fs::write(&path, concat!("km,source\n", "12000,web\n", "\n", "48000.5\n"))?;
+    assert_eq!(read_inputs::<f64>(&path.to_string_lossy())?, vec![12000.0, 48000.5]);
+    std::fs::write(&path, concat!("12000\n", "lots\n"))?;
+    let err = read_inputs::<f64>(&path.to_string_lossy()).unwrap_err();
+    std::fs::remove_file(&path)?;
+    assert!(matches!(err, LinearRegressionError::Parse { row: 2, .. }), "{:?}", err);
+    let missing = read_inputs::<f64>(&path.to_string_lossy());
+    assert!(matches!(missing, Err(LinearRegressionError::DatasetNotFound { .. })), "{:?}", missing);
+    Ok(())
   }
-}
\ No newline at end of file
+}
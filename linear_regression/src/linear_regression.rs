@@ -1,108 +1,516 @@
-use crate::utils::{load_dataset, normalize_dataset};
+use crate::error::{LinearRegressionError, Result};
+use crate::utils::normalize_dataset;
 use serde::{Serialize, Deserialize};
-use crate::normalization::NormalizationFactors;
+use crate::normalization::{NormalizationFactors, Scaler};
+use crate::dataset::{Dataset, DatasetFingerprint};
+use crate::drift::FeatureDistribution;
+use crate::optimizer::{Optimizer, OptimizerState, StoppingCriteria};
+use crate::loss::{Loss, Regularization};
+use crate::gradient_check::{check_gradient, GradientCheck};
+use crate::float::{Float, Precision};
+use crate::columns::Columns;
+use crate::sufficient_stats::SufficientStats;
+use crate::observer::{NoopObserver, TrainingControl, TrainingObserver, TrainingStep};
+use crate::history::TrainingHistory;
+use crate::imputation::Imputer;
+use crate::prediction::{ClampPolicy, Extrapolation, Prediction};
+
+/// Rows `partial_fit` keeps, most recent last. Older rows only remain in
+/// the sufficient statistics.
+pub const PARTIAL_FIT_WINDOW: usize = 10_000;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
-pub struct LinearRegression {
-  theta0: f64,
-  theta1: f64,
-  learning_rate: f64,
-  data: Vec<(f64, f64)>,
-  costs: Vec<f64>,
-  normalization: NormalizationFactors,
+#[serde(bound = "T: Float")]
+pub struct LinearRegression<T: Float = f64> {
+  theta0: T,
+  theta1: T,
+  learning_rate: T,
+  data: Columns<T>,
+  #[serde(default)]
+  history: TrainingHistory<T>,
+  /// Set once the model has been trained or given parameters
+  #[serde(default)]
+  trained: bool,
+  normalization: NormalizationFactors<T>,
+  #[serde(default)]
+  loss: Loss,
+  #[serde(default)]
+  regularization: Regularization,
+  #[serde(default)]
+  stats: SufficientStats<T>,
+  #[serde(default)]
+  optimizer: Optimizer,
+  #[serde(default)]
+  optimizer_state: OptimizerState<T>,
+  #[serde(default)]
+  stopping: StoppingCriteria,
+  #[serde(default)]
+  imputation: Option<Imputer>,
+  #[serde(default)]
+  dataset: Option<DatasetFingerprint>,
+  #[serde(default)]
+  clamp: ClampPolicy,
+  #[serde(default)]
+  distribution: Option<FeatureDistribution>,
+}
+
+/// Configures a `LinearRegression` before it is fitted.
+///
+/// ```ignore
+/// let mut model = LinearRegression::builder()
+///   .learning_rate(0.5)
+///   .optimizer(Optimizer::adam())
+///   .scaler(Scaler::Standard)
+///   .build();
+/// model.fit(&dataset)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct LinearRegressionBuilder<T: Float = f64> {
+  learning_rate: T,
+  optimizer: Optimizer,
+  scaler: Scaler,
+  stopping: StoppingCriteria,
+  loss: Loss,
+  regularization: Regularization,
+  clamp: ClampPolicy,
+}
+
+impl<T: Float> Default for LinearRegressionBuilder<T> {
+  fn default() -> Self {
+    Self {
+      learning_rate: T::of(0.001),
+      optimizer: Optimizer::default(),
+      scaler: Scaler::default(),
+      stopping: StoppingCriteria::default(),
+      loss: Loss::default(),
+      regularization: Regularization::default(),
+      clamp: ClampPolicy::default(),
+    }
+  }
+}
+
+impl<T: Float> LinearRegressionBuilder<T> {
+  pub fn learning_rate(mut self, learning_rate: T) -> Self {
+    self.learning_rate = learning_rate;
+    self
+  }
+
+  pub fn optimizer(mut self, optimizer: Optimizer) -> Self {
+    self.optimizer = optimizer;
+    self
+  }
+
+  pub fn scaler(mut self, scaler: Scaler) -> Self {
+    self.scaler = scaler;
+    self
+  }
+
+  pub fn stopping(mut self, stopping: StoppingCriteria) -> Self {
+    self.stopping = stopping;
+    self
+  }
+
+  /// Shorthand for setting `StoppingCriteria::max_iterations`
+  pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+    self.stopping.max_iterations = max_iterations;
+    self
+  }
+
+  pub fn loss(mut self, loss: Loss) -> Self {
+    self.loss = loss;
+    self
+  }
+
+  pub fn regularization(mut self, regularization: Regularization) -> Self {
+    self.regularization = regularization;
+    self
+  }
+
+  /// Bounds applied to the model's predictions
+  pub fn clamp(mut self, clamp: ClampPolicy) -> Self {
+    self.clamp = clamp;
+    self
+  }
+
+  /// Creates an unfitted model with this configuration
+  pub fn build(self) -> LinearRegression<T> {
+    LinearRegression {
+      learning_rate: self.learning_rate,
+      normalization: NormalizationFactors { scaler: self.scaler, ..NormalizationFactors::default() },
+      loss: self.loss,
+      regularization: self.regularization,
+      optimizer: self.optimizer,
+      stopping: self.stopping,
+      clamp: self.clamp,
+      ..LinearRegression::default()
+    }
+  }
 }
 
-impl LinearRegression {
-  /// Creates a new LinearRegression model with an optional learning rate.
-  pub fn new(learning_rate: Option<f64>) -> Result<Self, Box<dyn std::error::Error>> {
-    let data = load_dataset();
+impl<T: Float> LinearRegression<T> {
+  /// Creates a new, unfitted LinearRegression model with an optional learning rate.
+  pub fn new(learning_rate: Option<T>) -> Self {
+    let builder = Self::builder();
+    match learning_rate {
+      Some(learning_rate) => builder.learning_rate(learning_rate),
+      None => builder,
+    }
+    .build()
+  }
 
-    let factors = NormalizationFactors::from_data(&data);
-    let normalized_data = normalize_dataset(&data, &factors);
+  /// Starts configuring a model.
+  pub fn builder() -> LinearRegressionBuilder<T> {
+    LinearRegressionBuilder::default()
+  }
+
+  /// Fits the model to `dataset` from scratch, running up to
+  /// `StoppingCriteria::max_iterations` steps. Returns the number of steps taken.
+  pub fn fit(&mut self, dataset: &Dataset<T>) -> Result<usize> {
+    self.fit_with(dataset, &mut NoopObserver)
+  }
+
+  /// Like `fit`, reporting every step to `observer`.
+  pub fn fit_with<O>(&mut self, dataset: &Dataset<T>, observer: &mut O) -> Result<usize>
+  where
+    O: TrainingObserver<T> + ?Sized,
+  {
+    self.set_dataset(dataset)?;
+    self.theta0 = T::zero();
+    self.theta1 = T::zero();
+    self.history = TrainingHistory::default();
+    self.optimizer_state = OptimizerState::default();
+    self.trained = false;
+
+    self.train_with(self.stopping.max_iterations, observer)
+  }
+
+  /// Replaces the training samples, recomputing the normalization factors
+  /// with the configured scaler and taking over the dataset's imputation,
+  /// fingerprint and feature distribution. Parameters are kept as they are.
+  pub fn set_dataset(&mut self, dataset: &Dataset<T>) -> Result<()> {
+    if dataset.is_empty() {
+      return Err(LinearRegressionError::EmptyDataset);
+    }
 
-    Ok(Self {
-        theta0: 0.0,
-        theta1: 0.0,
-        learning_rate: learning_rate.unwrap_or(0.001),
-        data: normalized_data,
-        costs: Vec::new(),
-        normalization: factors,
-    })
+    let factors = NormalizationFactors::fit(dataset.records(), self.normalization.scaler);
+    factors.check_feature_range()?;
+    self.data = Columns::from_pairs(&normalize_dataset(dataset.records(), &factors));
+    self.stats = SufficientStats::from_columns(&self.data);
+    self.normalization = factors;
+    self.imputation = dataset.imputation().copied();
+    self.dataset = Some(dataset.fingerprint());
+    self.distribution = FeatureDistribution::of(&dataset.records().iter().map(|&(x, _)| x).collect::<Vec<T>>());
+    Ok(())
   }
 
+  /// Attaches `dataset` like `set_dataset`, but keeps the current fit: θ is
+  /// re-expressed in the dataset's normalization, so predictions are
+  /// unchanged, and the model's own imputation, fingerprint and feature
+  /// distribution are kept.
+  /// Used to plot or keep training a saved model.
+  pub fn attach_dataset(&mut self, dataset: &Dataset<T>) -> Result<()> {
+    let (params, old) = ((self.theta0, self.theta1), self.normalization.clone());
+    let (imputation, fingerprint, distribution) = (self.imputation, self.dataset.clone(), self.distribution.clone());
+    self.set_dataset(dataset)?;
+    (self.theta0, self.theta1) = reexpress_params(params, &old, &self.normalization);
+    self.imputation = imputation;
+    self.dataset = fingerprint;
+    self.distribution = distribution;
+    Ok(())
+  }
 
   /// Trains the model using gradient descent.
-  pub fn train(&mut self, iterations: usize) {
-    let m = self.data.len() as f64;
-    if m == 0.0 {
-      eprintln!("Error: No dataset loaded.");
-      return;
+  /// Returns the number of steps taken.
+  pub fn train(&mut self, iterations: usize) -> Result<usize> {
+    self.train_with(iterations, &mut NoopObserver)
+  }
+
+  /// Trains the model using gradient descent, reporting every step to
+  /// `observer`, which may stop training early.
+  /// Returns the number of steps taken.
+  pub fn train_with<O>(&mut self, iterations: usize, observer: &mut O) -> Result<usize>
+  where
+    O: TrainingObserver<T> + ?Sized,
+  {
+    if self.data.is_empty() {
+      return Err(LinearRegressionError::EmptyDataset);
     }
 
+    let steps = self.descend(iterations, observer)?;
+    self.trained = true;
+    Ok(steps)
+  }
+
+  /// Runs the gradient descent loop of `train_with`.
+  fn descend<O>(&mut self, iterations: usize, observer: &mut O) -> Result<usize>
+  where
+    O: TrainingObserver<T> + ?Sized,
+  {
     for i in 0..iterations {
       let current_cost = self.compute_cost();
+      check_cost(i, current_cost)?;
+      let gradient = self.compute_gradient();
+
+      if self.should_stop(i, current_cost, gradient) {
+        return Ok(i);
+      }
+
+      let step = self.step_snapshot(i, current_cost, gradient);
+      self.apply_step(&step);
+
+      if observer.on_step(&step) == TrainingControl::Stop {
+        return Ok(i + 1);
+      }
+    }
+    Ok(iterations)
+  }
+
+  /// Describes a step about to be taken from the current parameters.
+  pub(crate) fn step_snapshot(&self, iteration: usize, cost: T, gradient: (T, T)) -> TrainingStep<T> {
+    TrainingStep {
+      iteration,
+      theta: (self.theta0, self.theta1),
+      cost,
+      gradient,
+      learning_rate: self.learning_rate,
+    }
+  }
+
+  /// Stop if the reduction since the previous iteration of the current
+  /// run is below the tolerance, or if the gradient vanished.
+  pub(crate) fn should_stop(&self, iteration: usize, cost: T, (grad_theta0, grad_theta1): (T, T)) -> bool {
+    let tolerance = T::of(self.stopping.tolerance);
+    (iteration > 0 && self.history.last_cost().is_some_and(|previous| (previous - cost).abs() < tolerance)) ||
+      grad_theta0.hypot(grad_theta1) < T::of(self.stopping.min_gradient_norm)
+  }
+
+  /// Takes the optimizer step described by `step` and records it.
+  pub(crate) fn apply_step(&mut self, step: &TrainingStep<T>) {
+    let (delta_theta0, delta_theta1) =
+      self.optimizer.update(&mut self.optimizer_state, step.gradient, step.learning_rate);
+    self.theta0 -= delta_theta0;
+    self.theta1 -= delta_theta1;
+    self.trained = true;
+
+    // Store training history
+    self.history.record(step);
+  }
+
+  /// Updates the model from a batch of new `(mileage, price)` samples
+  /// without reloading the dataset.
+  ///
+  /// The normalization factors are widened to cover the batch, and its
+  /// mileages are merged into the feature distribution. When the factors
+  /// change, θ, the stored samples and their statistics are re-expressed in
+  /// the new scale so the fitted line is preserved. Gradient descent then
+  /// runs until the stopping criteria are met. With squared loss it runs on
+  /// the sufficient statistics of every sample seen, so repeated calls
+  /// approach `fit` on all of them. Only the latest `PARTIAL_FIT_WINDOW`
+  /// rows are kept; other losses, and raw `f32` data where the statistics
+  /// are inaccurate, train on those.
+  ///
+  /// Fails with `NonFiniteSample`, leaving the model unchanged, when a
+  /// sample holds NaN or an infinity.
+  pub fn partial_fit(&mut self, samples: &[(T, T)]) -> Result<()> {
+    if let Some(index) = samples.iter().position(|&(x, y)| !x.is_finite() || !y.is_finite()) {
+      return Err(LinearRegressionError::NonFiniteSample { index });
+    }
+    if samples.is_empty() {
+      return Ok(());
+    }
+
+    if self.normalization.count == 0 && !self.is_trained() {
+      self.normalization = NormalizationFactors::fit(samples, self.normalization.scaler);
+    } else {
+      let factors = self.normalization.extended(samples);
+      if factors.same_scale(&self.normalization) {
+        self.normalization = factors;
+      } else {
+        self.rescale(factors);
+      }
+    }
+
+    let mileages: Vec<T> = samples.iter().map(|&(x, _)| x).collect();
+    self.distribution = match &self.distribution {
+      Some(distribution) => Some(distribution.merged(&mileages)),
+      None => FeatureDistribution::of(&mileages),
+    };
 
-      // Stop if cost is NaN or if the reduction is negligible
-      if current_cost.is_nan() || current_cost.is_infinite() || 
-         (i > 0 && (self.costs[i - 1] - current_cost).abs() < 1e-6) {
-        eprintln!(
-            "Iteration {}: Cost converged or is NaN. Stopping training.",
-            i
-        );
+    let batch = Columns::from_pairs(&normalize_dataset(samples, &self.normalization));
+    self.stats.add(&batch);
+    self.data.extend(batch);
+    self.data.keep_last(PARTIAL_FIT_WINDOW);
+
+    for iteration in 0..self.stopping.max_iterations {
+      let (cost, gradient) = self.incremental_cost_and_gradient();
+      check_cost(self.history.len(), cost)?;
+      if self.should_stop(iteration, cost, gradient) {
         break;
       }
+      let step = self.step_snapshot(self.history.len(), cost, gradient);
+      self.apply_step(&step);
+    }
+    self.trained = true;
+    Ok(())
+  }
 
-      let mut sum_error_theta0 = 0.0;
-      let mut sum_error_theta1 = 0.0;
+  /// Feeds a stream of samples to `partial_fit` in batches of `batch_size`.
+  /// Returns the number of samples consumed.
+  pub fn partial_fit_stream<I>(&mut self, rows: I, batch_size: usize) -> Result<usize>
+  where
+    I: IntoIterator<Item = (T, T)>,
+  {
+    let batch_size = batch_size.max(1);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut consumed = 0;
 
-      for &(mileage, price) in &self.data {
-        let error = (self.theta0 + self.theta1 * mileage) - price;
-        sum_error_theta0 += error;
-        sum_error_theta1 += error * mileage;
+    for row in rows {
+      batch.push(row);
+      if batch.len() == batch_size {
+        self.partial_fit(&batch)?;
+        consumed += batch.len();
+        batch.clear();
       }
+    }
+    if !batch.is_empty() {
+      self.partial_fit(&batch)?;
+      consumed += batch.len();
+    }
 
-      let alpha_div_m = self.learning_rate / m;
-      self.theta0 -= alpha_div_m * sum_error_theta0;
-      self.theta1 -= alpha_div_m * sum_error_theta1;
+    Ok(consumed)
+  }
 
-      // Store cost history
-      self.costs.push(current_cost);
+  /// J(θ) and ∇J(θ) for `partial_fit`: over every sample seen when the
+  /// sufficient statistics can stand in for them, else over the kept rows.
+  fn incremental_cost_and_gradient(&self) -> (T, (T, T)) {
+    let (theta0, theta1) = (self.theta0, self.theta1);
+    if self.loss == Loss::Squared && self.stats_are_accurate() {
+      let n = self.stats.n;
+      let cost = self.cost_from_sum(self.stats.squared_loss_sum(theta0, theta1), n, theta1);
+      (cost, self.gradient_from_sums(self.stats.squared_gradient_sum(theta0, theta1), n, theta1))
+    } else {
+      (self.cost_at(theta0, theta1, &self.data, None), self.gradient_at(theta0, theta1, &self.data, None))
+    }
+  }
+
+  /// Switches to new normalization factors, re-expressing θ, the stored
+  /// samples and their statistics so that predictions in original units
+  /// are unchanged.
+  fn rescale(&mut self, factors: NormalizationFactors<T>) {
+    let old = &self.normalization;
+    (self.theta0, self.theta1) = reexpress_params((self.theta0, self.theta1), old, &factors);
+
+    for x in self.data.x.iter_mut() {
+      *x = factors.normalize_x(old.denormalize_x(*x));
+    }
+    for y in self.data.y.iter_mut() {
+      *y = factors.normalize_y(old.denormalize_y(*y));
     }
+    // x' = (x·span + offset − offset') / span', and likewise for y
+    let x_map = (old.x_span() / factors.x_span(), (old.x_offset() - factors.x_offset()) / factors.x_span());
+    let y_map = (old.y_span() / factors.y_span(), (old.y_offset() - factors.y_offset()) / factors.y_span());
+    self.stats = self.stats.affine(x_map, y_map);
+
+    self.normalization = factors;
+  }
+
+  /// Computes the gradient ∇J(θ) = (∂J/∂θ₀, ∂J/∂θ₁) over the dataset.
+  pub fn compute_gradient(&self) -> (T, T) {
+    self.gradient_at(self.theta0, self.theta1, &self.data, Some(&self.stats))
   }
 
   /// Computes the cost function J(θ).
-  pub fn compute_cost(&self) -> f64 {
-    let m = self.data.len() as f64;
-    if m == 0.0 {
-      return f64::NAN;
+  pub fn compute_cost(&self) -> T {
+    self.cost_at(self.theta0, self.theta1, &self.data, Some(&self.stats))
+  }
+
+  /// Compares `compute_gradient` with central finite differences of
+  /// `compute_cost` for the model's loss and regularization.
+  pub fn check_gradient(&self, epsilon: T) -> GradientCheck<T> {
+    check_gradient(
+      |theta0, theta1| self.cost_at(theta0, theta1, &self.data, Some(&self.stats)),
+      |theta0, theta1| self.gradient_at(theta0, theta1, &self.data, Some(&self.stats)),
+      (self.theta0, self.theta1),
+      epsilon,
+    )
+  }
+
+  /// Cost J(θ) of the given parameters over `data`.
+  ///
+  /// When `stats` summarizes `data` and the loss is squared, the cost is
+  /// evaluated from the sufficient statistics in O(1) instead of per row.
+  fn cost_at(&self, theta0: T, theta1: T, data: &Columns<T>, stats: Option<&SufficientStats<T>>) -> T {
+    if data.is_empty() {
+      return T::nan();
     }
 
-    self.data
-      .iter()
-      .map(|&(x, y)| {
-        let prediction = self.theta0 + self.theta1 * x;
-        (prediction - y).powi(2)
-      })
-      .sum::<f64>()
-      / (2.0 * m)
+    let loss_sum = match self.usable_stats(data, stats) {
+      Some(stats) => stats.squared_loss_sum(theta0, theta1),
+      None => data.loss_sum(theta0, theta1, self.loss),
+    };
+
+    self.cost_from_sum(loss_sum, data.len(), theta1)
+  }
+
+  /// Turns a summed loss over `m` rows into J(θ).
+  pub(crate) fn cost_from_sum(&self, loss_sum: T, m: usize, theta1: T) -> T {
+    loss_sum / T::of(m as f64) + self.regularization.penalty(theta1)
+  }
+
+  /// Turns summed loss derivatives over `m` rows into ∇J(θ).
+  pub(crate) fn gradient_from_sums(&self, (sum_theta0, sum_theta1): (T, T), m: usize, theta1: T) -> (T, T) {
+    let m = T::of(m as f64);
+    (sum_theta0 / m, sum_theta1 / m + self.regularization.derivative(theta1))
+  }
+
+  /// Gradient of J(θ) at the given parameters over `data`, using `stats`
+  /// like `cost_at` does.
+  fn gradient_at(&self, theta0: T, theta1: T, data: &Columns<T>, stats: Option<&SufficientStats<T>>) -> (T, T) {
+    let sums = match self.usable_stats(data, stats) {
+      Some(stats) => stats.squared_gradient_sum(theta0, theta1),
+      None => data.gradient_sum(theta0, theta1, self.loss),
+    };
+
+    self.gradient_from_sums(sums, data.len(), theta1)
+  }
+
+  /// Sufficient statistics can only stand in for the rows with squared
+  /// loss, only if they cover every row (e.g. not a stale deserialized copy),
+  /// and only where the expanded sums stay accurate.
+  fn usable_stats<'a>(&self, data: &Columns<T>, stats: Option<&'a SufficientStats<T>>) -> Option<&'a SufficientStats<T>> {
+    stats.filter(|stats| self.loss == Loss::Squared && stats.n == data.len() && self.stats_are_accurate())
   }
 
-  pub fn compute_precision(&self) -> (f64, f64, f64, f64) {
+  /// The expanded sums subtract large, nearly equal terms. Normalized data
+  /// keeps them small enough for either precision, but raw mileages squared
+  /// lose the cost and gradient to cancellation in `f32`.
+  fn stats_are_accurate(&self) -> bool {
+    self.normalization.scaler != Scaler::None || T::PRECISION == Precision::F64
+  }
+
+  /// Returns the sufficient statistics of the normalized dataset.
+  pub fn get_sufficient_stats(&self) -> &SufficientStats<T> {
+    &self.stats
+  }
+
+  /// Returns (MAE, MSE, RMSE, R²) of the unclamped predictions over the
+  /// training dataset. Fails with `EmptyDataset` when it has no samples.
+  pub fn compute_precision(&self) -> Result<(T, T, T, T)> {
     let dataset = self.get_dataset();
-    let n = dataset.len() as f64;
+    let n = T::of(dataset.len() as f64);
 
     if dataset.is_empty() {
-      return (f64::NAN, f64::NAN, f64::NAN, f64::NAN);
+      return Err(LinearRegressionError::EmptyDataset);
     }
 
-    let mut sum_absolute_error = 0.0;
-    let mut sum_squared_error = 0.0;
-    let mut sum_total_variance = 0.0;
+    let mut sum_absolute_error = T::zero();
+    let mut sum_squared_error = T::zero();
+    let mut sum_total_variance = T::zero();
     
-    let mean_y = dataset.iter().map(|&(_, y)| y).sum::<f64>() / n;
+    let mean_y = dataset.iter().map(|&(_, y)| y).sum::<T>() / n;
 
     for &(x, real_y) in &dataset {
-      let predicted_y = self.predict(x);
+      let predicted_y = self.predict_unchecked(x);
       let error = real_y - predicted_y;
       sum_absolute_error += error.abs();
       sum_squared_error += error.powi(2);
@@ -112,46 +520,195 @@ impl LinearRegression {
     let mae = sum_absolute_error / n;
     let mse = sum_squared_error / n;
     let rmse = mse.sqrt();
-    let r2 = 1.0 - (sum_squared_error / sum_total_variance);
+    let r2 = T::one() - (sum_squared_error / sum_total_variance);
 
-    (mae, mse, rmse, r2)
+    Ok((mae, mse, rmse, r2))
   }
 
-  /// Predicts the price for a given mileage.
-  pub fn predict(&self, mileage: f64) -> f64 {
-    if self.theta0 == 0f64 && self.theta1 == 0f64 {
-      return 0.0f64;
+  /// Predicts the price for a given mileage, within the model's clamp
+  /// policy, flagging mileages outside the training range. Fails with
+  /// `Untrained` before the model has been trained or given parameters.
+  pub fn predict(&self, mileage: T) -> Result<Prediction<T>> {
+    if !self.is_trained() {
+      return Err(LinearRegressionError::Untrained);
     }
-    let normalized_x = (mileage - self.normalization.x_min) / (self.normalization.x_max - self.normalization.x_min);
+    let unclamped = self.predict_unchecked(mileage);
+    let extrapolation = self.get_training_range().and_then(|(min, max)| {
+      if mileage < min {
+        Some(Extrapolation::Below { min })
+      } else if mileage > max {
+        Some(Extrapolation::Above { max })
+      } else {
+        None
+      }
+    });
+    Ok(Prediction { value: self.clamp.apply(unclamped), unclamped, extrapolation })
+  }
+
+  /// The price on the fitted line for a given mileage, without clamping
+  /// or checks, e.g. to score or plot the line itself.
+  pub fn predict_unchecked(&self, mileage: T) -> T {
+    let normalized_x = self.normalization.normalize_x(mileage);
     let normalized_y = self.theta0 + self.theta1 * normalized_x;
     self.normalization.denormalize_y(normalized_y)
   }
 
+  /// Returns the loss used for training.
+  pub fn get_loss(&self) -> Loss {
+    self.loss
+  }
+
+  /// Sets the loss used for training.
+  pub fn set_loss(&mut self, loss: Loss) {
+    self.loss = loss;
+  }
+
+  /// Returns the regularization applied to θ₁.
+  pub fn get_regularization(&self) -> Regularization {
+    self.regularization
+  }
+
+  /// Sets the regularization applied to θ₁.
+  pub fn set_regularization(&mut self, regularization: Regularization) {
+    self.regularization = regularization;
+  }
+
+  /// Returns the factors used to scale samples into the model's space.
+  pub fn get_normalization(&self) -> &NormalizationFactors<T> {
+    &self.normalization
+  }
+
+  /// Sets the factors used to scale samples, e.g. ones saved with the
+  /// parameters, so a model can predict without its training data.
+  pub fn set_normalization(&mut self, normalization: NormalizationFactors<T>) {
+    self.normalization = normalization;
+  }
+
+  /// Returns how missing cells of the training data were filled in, if any were.
+  pub fn get_imputation(&self) -> Option<&Imputer> {
+    self.imputation.as_ref()
+  }
+
+  /// Sets the imputation to replay on missing inputs, e.g. one saved with the parameters.
+  pub fn set_imputation(&mut self, imputation: Option<Imputer>) {
+    self.imputation = imputation;
+  }
+
+  /// Returns the fingerprint of the dataset the model was last given.
+  pub fn get_dataset_fingerprint(&self) -> Option<&DatasetFingerprint> {
+    self.dataset.as_ref()
+  }
+
+  /// Sets the fingerprint of the dataset the model was trained on.
+  pub fn set_dataset_fingerprint(&mut self, fingerprint: Option<DatasetFingerprint>) {
+    self.dataset = fingerprint;
+  }
+
+  /// Returns the summary of the mileages the model was trained on, which
+  /// drift in prediction inputs is measured against.
+  pub fn get_feature_distribution(&self) -> Option<&FeatureDistribution> {
+    self.distribution.as_ref()
+  }
+
+  /// Sets the summary of the mileages the model was trained on.
+  pub fn set_feature_distribution(&mut self, distribution: Option<FeatureDistribution>) {
+    self.distribution = distribution;
+  }
+
+  /// Whether the model has been trained or given parameters.
+  pub fn is_trained(&self) -> bool {
+    self.trained
+  }
+
+  /// Overrides whether the model counts as trained, for files that record it.
+  pub(crate) fn set_trained(&mut self, trained: bool) {
+    self.trained = trained;
+  }
+
+  /// Returns the lowest and highest mileages the model was trained on, or
+  /// `None` when they are unknown, e.g. for a model imported without them.
+  pub fn get_training_range(&self) -> Option<(T, T)> {
+    (self.normalization.count > 0).then_some((self.normalization.x_min, self.normalization.x_max))
+  }
+
+  /// Returns the bounds applied to predictions.
+  pub fn get_clamp(&self) -> ClampPolicy {
+    self.clamp
+  }
+
+  /// Sets the bounds applied to predictions.
+  pub fn set_clamp(&mut self, clamp: ClampPolicy) {
+    self.clamp = clamp;
+  }
+
+  /// Returns the learning rate.
+  pub fn get_learning_rate(&self) -> T {
+    self.learning_rate
+  }
+
+  /// Returns the optimizer used for training.
+  pub fn get_optimizer(&self) -> Optimizer {
+    self.optimizer
+  }
+
+  /// Returns the criteria that end training.
+  pub fn get_stopping_criteria(&self) -> StoppingCriteria {
+    self.stopping
+  }
+
+  /// Returns the record of every training step taken so far.
+  pub fn get_history(&self) -> &TrainingHistory<T> {
+    &self.history
+  }
+
   /// Returns the model's parameters.
-  pub fn get_params(&self) -> (f64, f64) {
+  pub fn get_params(&self) -> (T, T) {
     (self.theta0, self.theta1)
   }
 
   /// Sets the model's parameters.
-  pub fn set_params(&mut self, theta0: f64, theta1: f64) {
+  pub fn set_params(&mut self, theta0: T, theta1: T) {
     self.theta0 = theta0;
     self.theta1 = theta1;
+    self.trained = true;
   }
   
-  /// Returns a reference to the dataset.
-  pub fn get_normalized_dataset(&self) -> &Vec<(f64, f64)> {
+  /// Returns the normalized dataset.
+  pub fn get_normalized_dataset(&self) -> Vec<(T, T)> {
+    self.data.to_pairs()
+  }
+
+  /// Returns the normalized dataset in struct-of-arrays form.
+  pub fn get_normalized_columns(&self) -> &Columns<T> {
     &self.data
   }
 
   /// Returns a reference to the dataset.
-  pub fn get_dataset(&self) -> Vec<(f64, f64)> {
-    // self.data.clone()
+  pub fn get_dataset(&self) -> Vec<(T, T)> {
     self.data
       .iter()
-      .map(|&(x, y)| (
+      .map(|(x, y)| (
         self.normalization.denormalize_x(x),
         self.normalization.denormalize_y(y)
       ))
       .collect()
   }
 }
+
+/// Fails with `NonFiniteCost` once training has diverged.
+pub(crate) fn check_cost<T: Float>(iteration: usize, cost: T) -> Result<()> {
+  if cost.is_finite() {
+    Ok(())
+  } else {
+    Err(LinearRegressionError::NonFiniteCost { iteration })
+  }
+}
+
+/// Re-expresses θ fitted under the normalization `from` for the
+/// normalization `to`, keeping the line in original units.
+pub(crate) fn reexpress_params<T: Float>((theta0, theta1): (T, T), from: &NormalizationFactors<T>, to: &NormalizationFactors<T>) -> (T, T) {
+  // Line in original units: price = intercept + slope * mileage
+  let slope = theta1 * from.y_span() / from.x_span();
+  let intercept = from.denormalize_y(theta0) - slope * from.x_offset();
+  (to.normalize_y(intercept + slope * to.x_offset()), slope * to.x_span() / to.y_span())
+}
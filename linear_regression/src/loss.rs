@@ -0,0 +1,80 @@
+use serde::{Serialize, Deserialize};
+use crate::float::Float;
+
+/// Per-sample loss applied to the prediction error `ŷ - y`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Loss {
+  /// ½·e², the classic least-squares cost
+  #[default]
+  Squared,
+  /// |e|, robust to outliers but not differentiable at 0
+  Absolute,
+  /// Quadratic within `delta` of 0, linear beyond it
+  Huber { delta: f64 },
+}
+
+impl Loss {
+  /// Loss of a single prediction error
+  pub fn value<T: Float>(&self, error: T) -> T {
+    match *self {
+      Loss::Squared => T::of(0.5) * error * error,
+      Loss::Absolute => error.abs(),
+      Loss::Huber { delta } => {
+        let delta = T::of(delta);
+        if error.abs() <= delta {
+          T::of(0.5) * error * error
+        } else {
+          delta * (error.abs() - T::of(0.5) * delta)
+        }
+      }
+    }
+  }
+
+  /// Derivative of the loss with respect to the prediction error
+  pub fn derivative<T: Float>(&self, error: T) -> T {
+    match *self {
+      Loss::Squared => error,
+      Loss::Absolute => error.signum(),
+      Loss::Huber { delta } => error.max(-T::of(delta)).min(T::of(delta)),
+    }
+  }
+}
+
+/// Penalty on the slope θ₁ added to the cost. The intercept is never penalized.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Regularization {
+  #[default]
+  None,
+  /// Lasso: λ·|θ₁|
+  L1 { lambda: f64 },
+  /// Ridge: ½·λ·θ₁²
+  L2 { lambda: f64 },
+  /// Mix of L1 and L2 weighted by `l1_ratio` in `[0, 1]`
+  ElasticNet { lambda: f64, l1_ratio: f64 },
+}
+
+impl Regularization {
+  /// Penalty for the given slope
+  pub fn penalty<T: Float>(&self, theta1: T) -> T {
+    match *self {
+      Regularization::None => T::zero(),
+      Regularization::L1 { lambda } => T::of(lambda) * theta1.abs(),
+      Regularization::L2 { lambda } => T::of(0.5 * lambda) * theta1 * theta1,
+      Regularization::ElasticNet { lambda, l1_ratio } => {
+        T::of(lambda * l1_ratio) * theta1.abs() + T::of(0.5 * lambda * (1.0 - l1_ratio)) * theta1 * theta1
+      }
+    }
+  }
+
+  /// Derivative of the penalty with respect to the slope
+  pub fn derivative<T: Float>(&self, theta1: T) -> T {
+    match *self {
+      Regularization::None => T::zero(),
+      Regularization::L1 { lambda } => T::of(lambda) * theta1.signum(),
+      Regularization::L2 { lambda } => T::of(lambda) * theta1,
+      Regularization::ElasticNet { lambda, l1_ratio } => {
+        T::of(lambda * l1_ratio) * theta1.signum() + T::of(lambda * (1.0 - l1_ratio)) * theta1
+      }
+    }
+  }
+}
@@ -0,0 +1,251 @@
+use std::io::{self, Write};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use crate::dataset::DatasetFingerprint;
+use crate::drift::FeatureDistribution;
+use crate::error::{LinearRegressionError, Result};
+use crate::float::{Float, Precision};
+use crate::imputation::Imputer;
+use crate::linear_regression::LinearRegression;
+use crate::loss::{Loss, Regularization};
+use crate::normalization::NormalizationFactors;
+use crate::optimizer::{Optimizer, StoppingCriteria};
+use crate::persistence::{read_locked, write_atomically};
+use crate::prediction::ClampPolicy;
+
+/// Version of the model file layout written by this crate. Files with a
+/// higher version are rejected rather than misread.
+pub const MODEL_FORMAT_VERSION: u32 = 1;
+
+/// The kind of model a file holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelKind {
+  #[default]
+  LinearRegression,
+}
+
+/// How the model was trained.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrainingConfig {
+  pub learning_rate: f64,
+  pub optimizer: Optimizer,
+  pub stopping: StoppingCriteria,
+  pub loss: Loss,
+  pub regularization: Regularization,
+  /// Steps actually taken
+  pub iterations: usize,
+}
+
+/// Fit of the model on its training data, in the data's units.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Metrics {
+  pub mae: f64,
+  pub mse: f64,
+  pub rmse: f64,
+  pub r2: f64,
+  /// Training cost at the last step
+  pub final_cost: Option<f64>,
+}
+
+impl Metrics {
+  /// Scores `model` on `samples`, given in the data's units. Returns
+  /// `None` without samples. The final cost is left unknown.
+  pub fn evaluate<T: Float>(model: &LinearRegression<T>, samples: &[(T, T)]) -> Option<Self> {
+    if samples.is_empty() {
+      return None;
+    }
+    let n = samples.len() as f64;
+    let mean_y = samples.iter().map(|&(_, y)| y.as_f64()).sum::<f64>() / n;
+    let (mut absolute, mut squared, mut total) = (0.0, 0.0, 0.0);
+    for &(x, y) in samples {
+      let error = y.as_f64() - model.predict_unchecked(x).as_f64();
+      absolute += error.abs();
+      squared += error * error;
+      total += (y.as_f64() - mean_y).powi(2);
+    }
+
+    Some(Self {
+      mae: absolute / n,
+      mse: squared / n,
+      rmse: (squared / n).sqrt(),
+      r2: 1.0 - squared / total,
+      final_cost: None,
+    })
+  }
+}
+
+/// A saved model holding everything needed to predict: parameters,
+/// normalization and imputation, plus how, when and on what it was trained.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "T: Float")]
+pub struct ModelFile<T: Float = f64> {
+  pub format_version: u32,
+  pub kind: ModelKind,
+  pub precision: Precision,
+  /// Parameters, in the normalized space described by `normalization`
+  pub theta0: T,
+  pub theta1: T,
+  /// False when the parameters were never fitted, which only legacy
+  /// parameter files can hold
+  #[serde(default = "trained_by_default")]
+  pub trained: bool,
+  /// Scaler and the factors it was fitted with
+  pub normalization: NormalizationFactors<T>,
+  pub imputation: Option<Imputer>,
+  /// Bounds applied to predictions
+  #[serde(default)]
+  pub clamp: ClampPolicy,
+  pub training: TrainingConfig,
+  /// `None` when the model was trained without its samples in memory
+  pub metrics: Option<Metrics>,
+  pub dataset: Option<DatasetFingerprint>,
+  /// Deciles of the training mileages, to detect drift in prediction inputs
+  #[serde(default)]
+  pub distribution: Option<FeatureDistribution>,
+  pub created_at: DateTime<Utc>,
+  /// When the file was last saved
+  pub updated_at: DateTime<Utc>,
+}
+
+/// Model files written before `trained` was recorded only held fitted models.
+fn trained_by_default() -> bool {
+  true
+}
+
+/// The fields read before the rest of the file, to check it can be understood.
+#[derive(Deserialize)]
+struct ModelHeader {
+  format_version: u32,
+  precision: Precision,
+}
+
+impl<T: Float> ModelFile<T> {
+  /// Captures `model`, timestamped now.
+  pub fn from_model(model: &LinearRegression<T>) -> Self {
+    let (theta0, theta1) = model.get_params();
+    let metrics = Metrics::evaluate(model, &model.get_dataset()).map(|metrics| Metrics {
+      final_cost: model.get_history().last_cost().map(T::as_f64),
+      ..metrics
+    });
+    let now = Utc::now();
+
+    Self {
+      format_version: MODEL_FORMAT_VERSION,
+      kind: ModelKind::LinearRegression,
+      precision: T::PRECISION,
+      theta0,
+      theta1,
+      trained: model.is_trained(),
+      normalization: model.get_normalization().clone(),
+      imputation: model.get_imputation().copied(),
+      clamp: model.get_clamp(),
+      training: TrainingConfig {
+        learning_rate: model.get_learning_rate().as_f64(),
+        optimizer: model.get_optimizer(),
+        stopping: model.get_stopping_criteria(),
+        loss: model.get_loss(),
+        regularization: model.get_regularization(),
+        iterations: model.get_history().len(),
+      },
+      metrics,
+      dataset: model.get_dataset_fingerprint().cloned(),
+      distribution: model.get_feature_distribution().cloned(),
+      created_at: now,
+      updated_at: now,
+    }
+  }
+
+  /// Rebuilds a model that predicts like the saved one, without its training data.
+  pub fn to_model(&self) -> LinearRegression<T> {
+    let mut model = LinearRegression::builder()
+      .learning_rate(T::of(self.training.learning_rate))
+      .optimizer(self.training.optimizer)
+      .stopping(self.training.stopping)
+      .loss(self.training.loss)
+      .regularization(self.training.regularization)
+      .clamp(self.clamp)
+      .build();
+    model.set_params(self.theta0, self.theta1);
+    model.set_trained(self.trained);
+    model.set_normalization(self.normalization.clone());
+    model.set_imputation(self.imputation);
+    model.set_dataset_fingerprint(self.dataset.clone());
+    model.set_feature_distribution(self.distribution.clone());
+    model
+  }
+
+  /// The same model in the precision `U`, e.g. to use an `f32` model
+  /// where `f64` ones are expected.
+  pub fn cast<U: Float>(&self) -> ModelFile<U> {
+    ModelFile {
+      format_version: self.format_version,
+      kind: self.kind,
+      precision: U::PRECISION,
+      theta0: U::of(self.theta0.as_f64()),
+      theta1: U::of(self.theta1.as_f64()),
+      trained: self.trained,
+      normalization: self.normalization.cast(),
+      imputation: self.imputation,
+      clamp: self.clamp,
+      training: self.training,
+      metrics: self.metrics,
+      dataset: self.dataset.clone(),
+      distribution: self.distribution.clone(),
+      created_at: self.created_at,
+      updated_at: self.updated_at,
+    }
+  }
+
+  /// Writes the model to `path` as pretty-printed JSON, stamping
+  /// `updated_at`. The file is replaced atomically and its previous version
+  /// kept as a backup.
+  pub fn save(&mut self, path: &str) -> Result<()> {
+    self.updated_at = Utc::now();
+    write_atomically(path, |writer| {
+      serde_json::to_writer_pretty(&mut *writer, self).map_err(|err| LinearRegressionError::json(path, err))?;
+      writeln!(writer)?;
+      Ok(())
+    })
+  }
+
+  /// Reads the model file at `path`. Fails with `VersionMismatch` when it
+  /// was written by a newer version of the crate or in another precision.
+  pub fn load(path: &str) -> Result<Self> {
+    let contents = read_model_file(path)?;
+    let header = parse_header(path, &contents)?;
+    if header.precision != T::PRECISION {
+      return Err(LinearRegressionError::VersionMismatch {
+        path: path.to_string(),
+        expected: T::PRECISION.to_string(),
+        found: header.precision.to_string(),
+      });
+    }
+    serde_json::from_str(&contents).map_err(|err| LinearRegressionError::json(path, err))
+  }
+}
+
+/// Reads the precision the model file at `path` was saved in, so it can be
+/// loaded with the matching `ModelFile<T>`.
+pub fn model_precision(path: &str) -> Result<Precision> {
+  Ok(parse_header(path, &read_model_file(path)?)?.precision)
+}
+
+fn read_model_file(path: &str) -> Result<String> {
+  read_locked(path).map_err(|err| match err.kind() {
+    io::ErrorKind::NotFound => LinearRegressionError::ModelNotFound { path: path.to_string() },
+    _ => err.into(),
+  })
+}
+
+fn parse_header(path: &str, contents: &str) -> Result<ModelHeader> {
+  let header: ModelHeader = serde_json::from_str(contents).map_err(|err| LinearRegressionError::json(path, err))?;
+  if header.format_version > MODEL_FORMAT_VERSION {
+    return Err(LinearRegressionError::VersionMismatch {
+      path: path.to_string(),
+      expected: format!("model format {} or older", MODEL_FORMAT_VERSION),
+      found: format!("model format {}", header.format_version),
+    });
+  }
+  Ok(header)
+}
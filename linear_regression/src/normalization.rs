@@ -1,42 +1,211 @@
 use serde::{Serialize, Deserialize};
+use crate::float::Float;
+use crate::error::{LinearRegressionError, Result};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct NormalizationFactors {
-  pub x_min: f64,
-  pub x_max: f64,
-  pub y_min: f64,
-  pub y_max: f64,
+/// How samples are scaled before training.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Scaler {
+  /// Maps each column onto `[0, 1]` using its min and max
+  #[default]
+  MinMax,
+  /// Centers each column on its mean and divides by its standard deviation
+  Standard,
+  /// Trains on the raw values
+  None,
 }
 
-impl Default for NormalizationFactors {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "T: Float")]
+pub struct NormalizationFactors<T: Float = f64> {
+  pub x_min: T,
+  pub x_max: T,
+  pub y_min: T,
+  pub y_max: T,
+  #[serde(default)]
+  pub scaler: Scaler,
+  /// Number of samples the statistics below were computed from
+  #[serde(default)]
+  pub count: usize,
+  #[serde(default)]
+  pub x_mean: T,
+  #[serde(default)]
+  pub x_std: T,
+  #[serde(default)]
+  pub y_mean: T,
+  #[serde(default)]
+  pub y_std: T,
+}
+
+impl<T: Float> Default for NormalizationFactors<T> {
   fn default() -> Self {
     Self {
-      x_min: 0.0,
-      x_max: 1.0,
-      y_min: 0.0,
-      y_max: 1.0,
+      x_min: T::zero(),
+      x_max: T::one(),
+      y_min: T::zero(),
+      y_max: T::one(),
+      scaler: Scaler::default(),
+      count: 0,
+      x_mean: T::zero(),
+      x_std: T::one(),
+      y_mean: T::zero(),
+      y_std: T::one(),
     }
   }
 }
 
-impl NormalizationFactors {
-  /// Computes normalization factors from the dataset
-  pub fn from_data(data: &[(f64, f64)]) -> Self {
-    let x_min = data.iter().map(|&(x, _)| x).fold(f64::INFINITY, f64::min);
-    let x_max = data.iter().map(|&(x, _)| x).fold(f64::NEG_INFINITY, f64::max);
-    let y_min = data.iter().map(|&(_, y)| y).fold(f64::INFINITY, f64::min);
-    let y_max = data.iter().map(|&(_, y)| y).fold(f64::NEG_INFINITY, f64::max);
+impl<T: Float> NormalizationFactors<T> {
+  /// Computes min-max normalization factors from the dataset
+  pub fn from_data(data: &[(T, T)]) -> Self {
+    Self::fit(data, Scaler::MinMax)
+  }
+
+  /// The same factors in the precision `U`
+  pub fn cast<U: Float>(&self) -> NormalizationFactors<U> {
+    let cast = |value: T| U::of(value.as_f64());
+    NormalizationFactors {
+      x_min: cast(self.x_min),
+      x_max: cast(self.x_max),
+      y_min: cast(self.y_min),
+      y_max: cast(self.y_max),
+      scaler: self.scaler,
+      count: self.count,
+      x_mean: cast(self.x_mean),
+      x_std: cast(self.x_std),
+      y_mean: cast(self.y_mean),
+      y_std: cast(self.y_std),
+    }
+  }
+
+  /// Computes normalization factors for `scaler` from the dataset
+  pub fn fit(data: &[(T, T)], scaler: Scaler) -> Self {
+    let x_min = data.iter().map(|&(x, _)| x).fold(T::infinity(), T::min);
+    let x_max = data.iter().map(|&(x, _)| x).fold(T::neg_infinity(), T::max);
+    let y_min = data.iter().map(|&(_, y)| y).fold(T::infinity(), T::min);
+    let y_max = data.iter().map(|&(_, y)| y).fold(T::neg_infinity(), T::max);
+
+    let count = data.len();
+    let n = T::of(count.max(1) as f64);
+    let x_mean = data.iter().map(|&(x, _)| x).sum::<T>() / n;
+    let y_mean = data.iter().map(|&(_, y)| y).sum::<T>() / n;
+    let x_std = (data.iter().map(|&(x, _)| (x - x_mean).powi(2)).sum::<T>() / n).sqrt();
+    let y_std = (data.iter().map(|&(_, y)| (y - y_mean).powi(2)).sum::<T>() / n).sqrt();
+
+    Self { x_min, x_max, y_min, y_max, scaler, count, x_mean, x_std, y_mean, y_std }
+  }
+
+  /// Returns these factors updated to also cover `data`, merging the
+  /// running means and standard deviations
+  pub fn extended(&self, data: &[(T, T)]) -> Self {
+    let batch = Self::fit(data, self.scaler);
+    if self.count == 0 {
+      return Self {
+        x_min: self.x_min.min(batch.x_min),
+        x_max: self.x_max.max(batch.x_max),
+        y_min: self.y_min.min(batch.y_min),
+        y_max: self.y_max.max(batch.y_max),
+        ..batch
+      };
+    }
+
+    let (na, nb) = (T::of(self.count as f64), T::of(batch.count as f64));
+    let n = na + nb;
+    let merge = |mean_a: T, std_a: T, mean_b: T, std_b: T| {
+      let delta = mean_b - mean_a;
+      let mean = mean_a + delta * nb / n;
+      let m2 = std_a * std_a * na + std_b * std_b * nb + delta * delta * na * nb / n;
+      (mean, (m2 / n).sqrt())
+    };
+    let (x_mean, x_std) = merge(self.x_mean, self.x_std, batch.x_mean, batch.x_std);
+    let (y_mean, y_std) = merge(self.y_mean, self.y_std, batch.y_mean, batch.y_std);
+
+    Self {
+      x_min: self.x_min.min(batch.x_min),
+      x_max: self.x_max.max(batch.x_max),
+      y_min: self.y_min.min(batch.y_min),
+      y_max: self.y_max.max(batch.y_max),
+      scaler: self.scaler,
+      count: self.count + batch.count,
+      x_mean,
+      x_std,
+      y_mean,
+      y_std,
+    }
+  }
+
+  /// Fails if every sample seen has the same mileage, leaving no range to fit a slope on.
+  pub fn check_feature_range(&self) -> Result<()> {
+    if self.x_min == self.x_max {
+      Err(LinearRegressionError::DegenerateFeatureRange { value: self.x_min.as_f64() })
+    } else {
+      Ok(())
+    }
+  }
+
+  /// True when both sets of factors map values identically
+  pub fn same_scale(&self, other: &Self) -> bool {
+    self.x_offset() == other.x_offset() && self.x_span() == other.x_span() &&
+      self.y_offset() == other.y_offset() && self.y_span() == other.y_span()
+  }
+
+  /// Normalizes a mileage value
+  pub fn normalize_x(&self, x: T) -> T {
+    (x - self.x_offset()) / self.x_span()
+  }
 
-    Self { x_min, x_max, y_min, y_max }
+  /// Normalizes a price
+  pub fn normalize_y(&self, y: T) -> T {
+    (y - self.y_offset()) / self.y_span()
   }
 
   /// Denormalizes a mileage value
-  pub fn denormalize_x(&self, x: f64) -> f64 {
-    x * (self.x_max - self.x_min) + self.x_min
+  pub fn denormalize_x(&self, x: T) -> T {
+    x * self.x_span() + self.x_offset()
   }
 
   /// Denormalizes a predicted price
-  pub fn denormalize_y(&self, y: f64) -> f64 {
-    y * (self.y_max - self.y_min) + self.y_min
+  pub fn denormalize_y(&self, y: T) -> T {
+    y * self.y_span() + self.y_offset()
+  }
+
+  /// Mileage that maps to 0
+  pub fn x_offset(&self) -> T {
+    match self.scaler {
+      Scaler::MinMax => self.x_min,
+      Scaler::Standard => self.x_mean,
+      Scaler::None => T::zero(),
+    }
+  }
+
+  /// Price that maps to 0
+  pub fn y_offset(&self) -> T {
+    match self.scaler {
+      Scaler::MinMax => self.y_min,
+      Scaler::Standard => self.y_mean,
+      Scaler::None => T::zero(),
+    }
+  }
+
+  /// Mileage distance that maps to 1
+  pub fn x_span(&self) -> T {
+    match self.scaler {
+      Scaler::MinMax => Self::nonzero(self.x_max - self.x_min),
+      Scaler::Standard => Self::nonzero(self.x_std),
+      Scaler::None => T::one(),
+    }
+  }
+
+  /// Price distance that maps to 1
+  pub fn y_span(&self) -> T {
+    match self.scaler {
+      Scaler::MinMax => Self::nonzero(self.y_max - self.y_min),
+      Scaler::Standard => Self::nonzero(self.y_std),
+      Scaler::None => T::one(),
+    }
+  }
+
+  /// Falls back to 1 when all values are equal (e.g. a single sample)
+  /// so scaling never divides by zero.
+  fn nonzero(span: T) -> T {
+    if span == T::zero() { T::one() } else { span }
   }
 }
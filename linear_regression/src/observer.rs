@@ -0,0 +1,98 @@
+use crate::float::Float;
+
+/// Snapshot of one gradient descent step, passed to a `TrainingObserver`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrainingStep<T: Float = f64> {
+  /// Index of the step within the current training run
+  pub iteration: usize,
+  /// Parameters (θ₀, θ₁) the cost and gradient were evaluated at
+  pub theta: (T, T),
+  pub cost: T,
+  pub gradient: (T, T),
+  pub learning_rate: T,
+}
+
+impl<T: Float> TrainingStep<T> {
+  /// Euclidean norm of the gradient
+  pub fn gradient_norm(&self) -> T {
+    self.gradient.0.hypot(self.gradient.1)
+  }
+}
+
+/// Whether training should go on after a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrainingControl {
+  Continue,
+  Stop,
+}
+
+/// Hook called after every gradient descent step.
+///
+/// Implemented for closures taking a `&TrainingStep`, for optional
+/// observers, and for pairs of observers so a progress bar and a logger
+/// can watch the same run.
+pub trait TrainingObserver<T: Float = f64> {
+  fn on_step(&mut self, step: &TrainingStep<T>) -> TrainingControl;
+}
+
+impl<T: Float, F> TrainingObserver<T> for F
+where
+  F: FnMut(&TrainingStep<T>) -> TrainingControl,
+{
+  fn on_step(&mut self, step: &TrainingStep<T>) -> TrainingControl {
+    self(step)
+  }
+}
+
+impl<T: Float, A: TrainingObserver<T>, B: TrainingObserver<T>> TrainingObserver<T> for (A, B) {
+  fn on_step(&mut self, step: &TrainingStep<T>) -> TrainingControl {
+    let first = self.0.on_step(step);
+    let second = self.1.on_step(step);
+    if first == TrainingControl::Stop || second == TrainingControl::Stop {
+      TrainingControl::Stop
+    } else {
+      TrainingControl::Continue
+    }
+  }
+}
+
+impl<T: Float, O: TrainingObserver<T>> TrainingObserver<T> for Option<O> {
+  fn on_step(&mut self, step: &TrainingStep<T>) -> TrainingControl {
+    match self {
+      Some(observer) => observer.on_step(step),
+      None => TrainingControl::Continue,
+    }
+  }
+}
+
+/// Observer that does nothing and never stops training.
+pub struct NoopObserver;
+
+impl<T: Float> TrainingObserver<T> for NoopObserver {
+  fn on_step(&mut self, _step: &TrainingStep<T>) -> TrainingControl {
+    TrainingControl::Continue
+  }
+}
+
+/// Observer that prints the cost and gradient norm every `every` steps.
+pub struct LogObserver {
+  every: usize,
+}
+
+impl LogObserver {
+  pub fn new(every: usize) -> Self {
+    Self { every: every.max(1) }
+  }
+}
+
+impl<T: Float> TrainingObserver<T> for LogObserver {
+  fn on_step(&mut self, step: &TrainingStep<T>) -> TrainingControl {
+    if step.iteration % self.every == 0 {
+      eprintln!(
+        "Iteration {}: cost = {:.6}, |∇J| = {:.6}, θ₀ = {:.4}, θ₁ = {:.4}",
+        step.iteration, step.cost, step.gradient_norm(), step.theta.0, step.theta.1
+      );
+    }
+    TrainingControl::Continue
+  }
+}
@@ -0,0 +1,84 @@
+use serde::{Serialize, Deserialize};
+use crate::float::Float;
+
+/// Rule turning a gradient into a parameter update.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Optimizer {
+  /// θ ← θ − α·∇J
+  #[default]
+  GradientDescent,
+  /// Accumulates a velocity v ← β·v + ∇J and steps θ ← θ − α·v
+  Momentum { beta: f64 },
+  /// Adaptive moment estimation with bias-corrected first and second moments
+  Adam { beta1: f64, beta2: f64, epsilon: f64 },
+}
+
+impl Optimizer {
+  /// Adam with the usual defaults (β₁ = 0.9, β₂ = 0.999, ε = 1e-8)
+  pub fn adam() -> Self {
+    Optimizer::Adam { beta1: 0.9, beta2: 0.999, epsilon: 1e-8 }
+  }
+
+  /// Returns the amount to subtract from (θ₀, θ₁) for `gradient`,
+  /// updating the optimizer's running `state`.
+  pub fn update<T: Float>(&self, state: &mut OptimizerState<T>, gradient: (T, T), learning_rate: T) -> (T, T) {
+    state.steps += 1;
+    match *self {
+      Optimizer::GradientDescent => (learning_rate * gradient.0, learning_rate * gradient.1),
+      Optimizer::Momentum { beta } => {
+        let beta = T::of(beta);
+        state.first.0 = beta * state.first.0 + gradient.0;
+        state.first.1 = beta * state.first.1 + gradient.1;
+        (learning_rate * state.first.0, learning_rate * state.first.1)
+      }
+      Optimizer::Adam { beta1, beta2, epsilon } => {
+        let (b1, b2) = (T::of(beta1), T::of(beta2));
+        let one = T::one();
+        state.first.0 = b1 * state.first.0 + (one - b1) * gradient.0;
+        state.first.1 = b1 * state.first.1 + (one - b1) * gradient.1;
+        state.second.0 = b2 * state.second.0 + (one - b2) * gradient.0 * gradient.0;
+        state.second.1 = b2 * state.second.1 + (one - b2) * gradient.1 * gradient.1;
+
+        let t = state.steps as i32;
+        let correction1 = one - b1.powi(t);
+        let correction2 = one - b2.powi(t);
+        let step = |first: T, second: T| {
+          learning_rate * (first / correction1) / ((second / correction2).sqrt() + T::of(epsilon))
+        };
+        (step(state.first.0, state.second.0), step(state.first.1, state.second.1))
+      }
+    }
+  }
+}
+
+/// Running moments kept between optimizer steps.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "T: Float")]
+pub struct OptimizerState<T: Float = f64> {
+  pub steps: usize,
+  /// Velocity (momentum) or first moment (Adam) for (θ₀, θ₁)
+  pub first: (T, T),
+  /// Second moment (Adam) for (θ₀, θ₁)
+  pub second: (T, T),
+}
+
+/// When gradient descent should stop.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StoppingCriteria {
+  /// Steps run by `fit`
+  pub max_iterations: usize,
+  /// Stop when the cost improves by less than this between steps
+  pub tolerance: f64,
+  /// Stop when the gradient norm falls below this
+  pub min_gradient_norm: f64,
+}
+
+impl Default for StoppingCriteria {
+  fn default() -> Self {
+    Self {
+      max_iterations: 1000,
+      tolerance: 1e-6,
+      min_gradient_norm: 0.0,
+    }
+  }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+use std::str::FromStr;
+use csv::StringRecord;
+use serde::{Serialize, Deserialize};
+use crate::error::{LinearRegressionError, Result};
+use crate::float::Float;
+use crate::formats::DatasetFormat;
+use crate::imputation::{MissingValues, PartialRow};
+
+/// Currency symbols stripped from numeric cells
+const CURRENCY_SYMBOLS: &[char] = &['$', '€', '£', '¥'];
+
+/// Picks a column by header name or by 0-based index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnSelector {
+  Name(String),
+  Index(usize),
+}
+
+impl FromStr for ColumnSelector {
+  type Err = String;
+
+  /// Numbers select by index, anything else by name
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    if s.is_empty() {
+      return Err("Empty column selector".to_string());
+    }
+    Ok(match s.parse() {
+      Ok(index) => ColumnSelector::Index(index),
+      Err(_) => ColumnSelector::Name(s.to_string()),
+    })
+  }
+}
+
+impl fmt::Display for ColumnSelector {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ColumnSelector::Name(name) => write!(f, "'{}'", name),
+      ColumnSelector::Index(index) => write!(f, "#{}", index),
+    }
+  }
+}
+
+/// What to do with a row that cannot be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BadRowPolicy {
+  /// Stop loading with a `Parse` error
+  #[default]
+  FailFast,
+  /// Leave the row out and record it in the `LoadReport`
+  Skip,
+}
+
+impl FromStr for BadRowPolicy {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_ascii_lowercase().as_str() {
+      "fail" => Ok(BadRowPolicy::FailFast),
+      "skip" => Ok(BadRowPolicy::Skip),
+      other => Err(format!("Unknown bad row policy '{}', expected fail or skip", other)),
+    }
+  }
+}
+
+/// How a dataset file is read. Delimiter, quote, comment and header
+/// settings only apply to CSV; TSV always uses tabs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadOptions {
+  pub feature: ColumnSelector,
+  pub target: ColumnSelector,
+  /// Whether the first row is a header. Without one, columns can only be selected by index.
+  pub has_headers: bool,
+  pub delimiter: u8,
+  pub quote: u8,
+  /// Lines starting with this character are ignored
+  pub comment: Option<u8>,
+  /// Digit grouping character removed from numbers, e.g. `,` in `12,500`
+  pub thousands_separator: Option<char>,
+  pub bad_rows: BadRowPolicy,
+  /// What to do with empty feature or target cells
+  pub missing: MissingValues,
+  /// File format; detected from the extension when `None`
+  pub format: Option<DatasetFormat>,
+}
+
+impl Default for LoadOptions {
+  fn default() -> Self {
+    Self {
+      feature: ColumnSelector::Index(0),
+      target: ColumnSelector::Index(1),
+      has_headers: true,
+      delimiter: b',',
+      quote: b'"',
+      comment: None,
+      thousands_separator: None,
+      bad_rows: BadRowPolicy::FailFast,
+      missing: MissingValues::Fail,
+      format: None,
+    }
+  }
+}
+
+impl LoadOptions {
+  /// Parses a numeric cell, accepting surrounding whitespace, currency
+  /// symbols and the configured thousands separator.
+  pub fn parse_number<T: Float>(&self, value: &str) -> Result<T, String> {
+    let mut cleaned: String = value.trim().trim_matches(CURRENCY_SYMBOLS).trim().to_string();
+    if let Some(separator) = self.thousands_separator {
+      cleaned.retain(|c| c != separator);
+    }
+    if cleaned.is_empty() {
+      return Err("missing value".to_string());
+    }
+    cleaned.parse().map_err(|err| format!("'{}' is not a number ({})", value, err))
+  }
+
+  /// Parses a cell that may be absent or blank. Such cells are `None`,
+  /// unless missing values are configured to fail.
+  pub(crate) fn parse_cell<T: Float>(&self, value: Option<&str>) -> Result<Option<T>, String> {
+    match value.map(str::trim) {
+      None | Some("") if self.missing != MissingValues::Fail => Ok(None),
+      None => Err("missing value".to_string()),
+      Some(value) => self.parse_number(value).map(Some),
+    }
+  }
+}
+
+/// A row left out of a dataset, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DroppedRow {
+  /// Line of the file the row was read from
+  pub line: usize,
+  /// 1-based column of the offending cell, when known
+  pub column: Option<usize>,
+  pub reason: String,
+}
+
+impl fmt::Display for DroppedRow {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.column {
+      Some(column) => write!(f, "line {}, column {}: {}", self.line, column, self.reason),
+      None => write!(f, "line {}: {}", self.line, self.reason),
+    }
+  }
+}
+
+/// A missing cell filled in while loading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImputedValue {
+  /// Line of the file the row was read from
+  pub line: usize,
+  /// Name of the column the cell belongs to
+  pub column: String,
+  pub value: f64,
+}
+
+impl fmt::Display for ImputedValue {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "line {}: {} imputed as {}", self.line, self.column, self.value)
+  }
+}
+
+/// Rows read from a dataset file, the ones left out and the cells filled in.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoadReport {
+  pub rows: usize,
+  pub dropped: Vec<DroppedRow>,
+  #[serde(default)]
+  pub imputed: Vec<ImputedValue>,
+}
+
+impl LoadReport {
+  /// True when every row was read as it is in the file
+  pub fn is_complete(&self) -> bool {
+    self.dropped.is_empty() && self.imputed.is_empty()
+  }
+}
+
+impl fmt::Display for LoadReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} rows loaded, {} dropped", self.rows, self.dropped.len())?;
+    if !self.imputed.is_empty() {
+      write!(f, ", {} values imputed", self.imputed.len())?;
+    }
+    for row in &self.dropped {
+      write!(f, "\n  {}", row)?;
+    }
+    for value in &self.imputed {
+      write!(f, "\n  {}", value)?;
+    }
+    Ok(())
+  }
+}
+
+/// Extracts the selected columns from records of one file.
+pub(crate) struct RecordParser<'a> {
+  path: &'a str,
+  options: &'a LoadOptions,
+  columns: (usize, usize),
+}
+
+impl<'a> RecordParser<'a> {
+  /// Resolves the selected columns against the file's `headers`.
+  pub(crate) fn new(path: &'a str, options: &'a LoadOptions, headers: Option<&StringRecord>) -> Result<Self> {
+    let resolve = |selector: &ColumnSelector| match (selector, headers) {
+      (ColumnSelector::Index(index), _) => Ok(*index),
+      (ColumnSelector::Name(name), Some(headers)) => headers
+        .iter()
+        .position(|header| header.trim() == name)
+        .ok_or_else(|| format!("no column named '{}'", name)),
+      (ColumnSelector::Name(name), None) => Err(format!("cannot select column '{}' in a file without a header", name)),
+    };
+    let column_error = |message| LinearRegressionError::Parse {
+      path: path.to_string(),
+      row: 1,
+      column: None,
+      message,
+    };
+
+    let columns = (
+      resolve(&options.feature).map_err(column_error)?,
+      resolve(&options.target).map_err(column_error)?,
+    );
+    Ok(Self { path, options, columns })
+  }
+
+  /// Header names of the selected columns
+  pub(crate) fn headers(&self, headers: &StringRecord) -> (String, String) {
+    let header = |column: usize| headers.get(column).unwrap_or_default().trim().to_string();
+    (header(self.columns.0), header(self.columns.1))
+  }
+
+  /// Parses one record. Returns `Ok(None)` when the row is bad and the
+  /// policy is to skip it, after recording it in `report`.
+  pub(crate) fn parse<T: Float>(
+    &self,
+    result: csv::Result<StringRecord>,
+    report: &mut LoadReport,
+  ) -> Result<Option<(usize, PartialRow<T>)>> {
+    apply_policy(self.path, self.options, self.try_parse(result), report)
+  }
+
+  fn try_parse<T: Float>(&self, result: csv::Result<StringRecord>) -> Result<(usize, PartialRow<T>), DroppedRow> {
+    let record = result.map_err(|err| DroppedRow {
+      line: err.position().map_or(0, |position| position.line() as usize),
+      column: None,
+      reason: err.to_string(),
+    })?;
+    let line = record.position().map_or(0, |position| position.line() as usize);
+
+    let field = |column: usize| -> Result<Option<T>, DroppedRow> {
+      self.options
+        .parse_cell(record.get(column))
+        .map_err(|reason| DroppedRow { line, column: Some(column + 1), reason })
+    };
+
+    Ok((line, (field(self.columns.0)?, field(self.columns.1)?)))
+  }
+}
+
+/// Counts a parsed row in `report`, or handles a bad one according to the
+/// bad row policy: recorded and skipped (`Ok(None)`), or turned into a `Parse` error.
+pub(crate) fn apply_policy<R>(
+  path: &str,
+  options: &LoadOptions,
+  row: Result<R, DroppedRow>,
+  report: &mut LoadReport,
+) -> Result<Option<R>> {
+  match row {
+    Ok(row) => {
+      report.rows += 1;
+      Ok(Some(row))
+    }
+    Err(dropped) if options.bad_rows == BadRowPolicy::Skip => {
+      report.dropped.push(dropped);
+      Ok(None)
+    }
+    Err(DroppedRow { line, column, reason }) => Err(LinearRegressionError::Parse {
+      path: path.to_string(),
+      row: line,
+      column,
+      message: reason,
+    }),
+  }
+}
@@ -0,0 +1,128 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use crate::error::Result;
+
+/// Appended to a file's path to name the copy of its previous version.
+pub const BACKUP_SUFFIX: &str = ".bak";
+
+/// Where `write_atomically` keeps the previous version of `path`.
+pub fn backup_path(path: &str) -> String {
+  format!("{}{}", path, BACKUP_SUFFIX)
+}
+
+/// Replaces the file at `path` with what `write` produces, without ever
+/// leaving it partly written: the contents go to a temporary file in the
+/// same directory, which is synced and then renamed over `path`.
+///
+/// The previous version, if any, is kept at `backup_path(path)`. Writers
+/// to the same path, in this process or another, take turns through an
+/// advisory lock on `<path>.lock`, which on Unix only exists while one is
+/// writing.
+pub fn write_atomically<F>(path: &str, write: F) -> Result<()>
+where
+  F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+  let _lock = FileLock::exclusive(path)?;
+  let temp = temp_path(path);
+  let result = (|| {
+    let mut writer = BufWriter::new(File::create(&temp)?);
+    write(&mut writer)?;
+    writer.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+    if Path::new(path).exists() {
+      fs::copy(path, backup_path(path))?;
+    }
+    fs::rename(&temp, path)?;
+    Ok(())
+  })();
+
+  if result.is_err() {
+    let _ = fs::remove_file(&temp);
+  }
+  result
+}
+
+/// Reads the file at `path`, waiting for a writer holding its lock to
+/// finish. Never creates or modifies any file.
+pub fn read_locked(path: &str) -> io::Result<String> {
+  let _lock = FileLock::shared(path)?;
+  fs::read_to_string(path)
+}
+
+/// `.<name>.<pid>.tmp` next to `path`, so the rename stays on one file system
+fn temp_path(path: &str) -> PathBuf {
+  let path = Path::new(path);
+  let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+  path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
+
+/// An advisory lock on `<path>.lock`, released when dropped. On Unix,
+/// writers remove the lock file as they release it, so none are left behind.
+struct FileLock {
+  _file: Option<File>,
+  /// The lock file to remove on drop, set for exclusive locks
+  path: Option<String>,
+}
+
+impl FileLock {
+  fn exclusive(path: &str) -> io::Result<Self> {
+    let path = lock_path(path);
+    loop {
+      let file = OpenOptions::new().create(true).truncate(false).write(true).open(&path)?;
+      #[cfg(not(target_arch = "wasm32"))]
+      fs4::fs_std::FileExt::lock_exclusive(&file)?;
+      // The previous holder may have removed the file while we waited for
+      // it, and a lock on a removed file keeps no one else out
+      if is_current(&file, &path)? {
+        // Only removed where waiting writers can tell it was replaced
+        return Ok(Self { _file: Some(file), path: cfg!(unix).then_some(path) });
+      }
+    }
+  }
+
+  /// Shares the lock with other readers. Files that are not being written
+  /// through `write_atomically` have no lock file and are read as they are.
+  fn shared(path: &str) -> io::Result<Self> {
+    match File::open(lock_path(path)) {
+      Ok(file) => {
+        #[cfg(not(target_arch = "wasm32"))]
+        fs4::fs_std::FileExt::lock_shared(&file)?;
+        Ok(Self { _file: Some(file), path: None })
+      }
+      Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self { _file: None, path: None }),
+      Err(err) => Err(err),
+    }
+  }
+}
+
+impl Drop for FileLock {
+  fn drop(&mut self) {
+    // Removed while still locked: `_file` is only closed after this
+    if let Some(path) = &self.path {
+      let _ = fs::remove_file(path);
+    }
+  }
+}
+
+fn lock_path(path: &str) -> String {
+  format!("{}.lock", path)
+}
+
+/// Whether `file` is still the lock file at `path`.
+#[cfg(unix)]
+fn is_current(file: &File, path: &str) -> io::Result<bool> {
+  use std::os::unix::fs::MetadataExt;
+
+  let locked = file.metadata()?;
+  match fs::metadata(path) {
+    Ok(current) => Ok((locked.dev(), locked.ino()) == (current.dev(), current.ino())),
+    Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+    Err(err) => Err(err),
+  }
+}
+
+/// Lock files are only removed on Unix, so elsewhere they stay current.
+#[cfg(not(unix))]
+fn is_current(_file: &File, _path: &str) -> io::Result<bool> {
+  Ok(true)
+}
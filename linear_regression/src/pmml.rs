@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use chrono::Utc;
+use quick_xml::events::{BytesDecl, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer, XmlVersion};
+use crate::drift::FeatureDistribution;
+use crate::export::check_finite;
+use crate::error::{LinearRegressionError, Result};
+use crate::float::{Float, Precision};
+use crate::imputation::Imputer;
+use crate::linear_regression::{reexpress_params, LinearRegression};
+use crate::normalization::{NormalizationFactors, Scaler};
+use crate::persistence::{read_locked, write_atomically};
+use crate::prediction::ClampPolicy;
+
+/// Namespace of the PMML version written.
+pub const PMML_NAMESPACE: &str = "http://www.dmg.org/PMML-4_4";
+
+/// `extender` of the PMML extensions that keep what standard PMML has no
+/// place for, so a model reads back exactly as it was written.
+const EXTENDER: &str = "ft_linear_regression";
+
+/// A regression model read from PMML, with the names of its fields.
+#[derive(Debug)]
+pub struct PmmlModel<T: Float = f64> {
+  pub model: LinearRegression<T>,
+  pub feature: String,
+  pub target: String,
+}
+
+/// Writes `model` as a PMML `RegressionModel` predicting `target` from
+/// `feature`. The regression table holds the coefficients in original
+/// units; a missing feature is replaced by the imputed value, if any, and
+/// the clamp policy becomes the target's `min` and `max`. Fails with
+/// `NonFiniteParameter` when one of these is NaN or infinite.
+pub fn to_pmml<T: Float>(model: &LinearRegression<T>, feature: &str, target: &str) -> Result<String> {
+  let (intercept, slope) = reexpress_params(model.get_params(), model.get_normalization(), &raw_units());
+  let (intercept, slope) = (literal("intercept", intercept)?, literal("coefficient", slope)?);
+  let data_type = match T::PRECISION {
+    Precision::F32 => "float",
+    Precision::F64 => "double",
+  };
+  let normalization = serde_json::to_string(model.get_normalization()).expect("normalization factors serialize to JSON");
+  let imputation = model.get_imputation().map(|imputation| {
+    serde_json::to_string(imputation).expect("imputations serialize to JSON")
+  });
+  let distribution = model.get_feature_distribution().map(|distribution| {
+    serde_json::to_string(distribution).expect("distributions serialize to JSON")
+  });
+  let replacement = model.get_imputation().map(|imputation| literal("missing value replacement", imputation.feature::<f64>(None)));
+  let replacement = replacement.transpose()?;
+  let clamp = model.get_clamp();
+  let floor = clamp.floor.map(|floor| literal("clamp floor", floor)).transpose()?;
+  let ceiling = clamp.ceiling.map(|ceiling| literal("clamp ceiling", ceiling)).transpose()?;
+
+  let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+  writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+  writer
+    .create_element("PMML")
+    .with_attributes([("xmlns", PMML_NAMESPACE), ("version", "4.4")])
+    .write_inner_content(|writer| {
+      writer
+        .create_element("Header")
+        .with_attribute(("description", format!("{} predicted from {}", target, feature).as_str()))
+        .write_inner_content(|writer| {
+          writer
+            .create_element("Application")
+            .with_attributes([("name", EXTENDER), ("version", env!("CARGO_PKG_VERSION"))])
+            .write_empty()?;
+          writer.create_element("Timestamp").write_text_content(BytesText::new(&Utc::now().to_rfc3339()))?;
+          Ok(())
+        })?;
+
+      writer
+        .create_element("DataDictionary")
+        .with_attribute(("numberOfFields", "2"))
+        .write_inner_content(|writer| {
+          for name in [feature, target] {
+            writer
+              .create_element("DataField")
+              .with_attributes([("name", name), ("optype", "continuous"), ("dataType", data_type)])
+              .write_empty()?;
+          }
+          Ok(())
+        })?;
+
+      writer
+        .create_element("RegressionModel")
+        .with_attributes([
+          ("modelName", EXTENDER),
+          ("functionName", "regression"),
+          ("algorithmName", "linearRegression"),
+        ])
+        .write_inner_content(|writer| {
+          extension(writer, "normalization", &normalization)?;
+          if let Some(imputation) = &imputation {
+            extension(writer, "imputation", imputation)?;
+          }
+          if let Some(distribution) = &distribution {
+            extension(writer, "distribution", distribution)?;
+          }
+          writer.create_element("MiningSchema").write_inner_content(|writer| {
+            let mut active = writer.create_element("MiningField").with_attributes([("name", feature), ("usageType", "active")]);
+            if let Some(replacement) = &replacement {
+              active = active.with_attribute(("missingValueReplacement", replacement.as_str()));
+            }
+            active.write_empty()?;
+            writer.create_element("MiningField").with_attributes([("name", target), ("usageType", "target")]).write_empty()?;
+            Ok(())
+          })?;
+          if !clamp.is_none() {
+            writer.create_element("Targets").write_inner_content(|writer| {
+              let mut bounds = writer.create_element("Target").with_attribute(("field", target));
+              if let Some(floor) = &floor {
+                bounds = bounds.with_attribute(("min", floor.as_str()));
+              }
+              if let Some(ceiling) = &ceiling {
+                bounds = bounds.with_attribute(("max", ceiling.as_str()));
+              }
+              bounds.write_empty()?;
+              Ok(())
+            })?;
+          }
+          writer
+            .create_element("RegressionTable")
+            .with_attribute(("intercept", intercept.as_str()))
+            .write_inner_content(|writer| {
+              writer
+                .create_element("NumericPredictor")
+                .with_attributes([("name", feature), ("exponent", "1"), ("coefficient", slope.as_str())])
+                .write_empty()?;
+              Ok(())
+            })?;
+          Ok(())
+        })?;
+      Ok(())
+    })?;
+
+  let mut xml = String::from_utf8(writer.into_inner()).expect("the PMML writer only writes UTF-8");
+  xml.push('\n');
+  Ok(xml)
+}
+
+/// Writes `model` to `path` as PMML, see `to_pmml`.
+pub fn save_pmml<T: Float>(model: &LinearRegression<T>, feature: &str, target: &str, path: &str) -> Result<()> {
+  let xml = to_pmml(model, feature, target)?;
+  write_atomically(path, |file| Ok(file.write_all(xml.as_bytes())?))
+}
+
+/// Reads a PMML document holding a linear `RegressionModel` with a single
+/// numeric predictor. Models written by `to_pmml` come back with their
+/// normalization, imputation and feature distribution; others train on
+/// raw values.
+pub fn from_pmml<T: Float>(xml: &str) -> Result<PmmlModel<T>> {
+  parse("PMML document", xml)
+}
+
+/// Reads the PMML file at `path`, see `from_pmml`.
+pub fn load_pmml<T: Float>(path: &str) -> Result<PmmlModel<T>> {
+  let xml = read_locked(path).map_err(|err| match err.kind() {
+    io::ErrorKind::NotFound => LinearRegressionError::ModelNotFound { path: path.to_string() },
+    _ => err.into(),
+  })?;
+  parse(path, &xml)
+}
+
+/// Identity factors: parameters in original units
+fn raw_units<T: Float>() -> NormalizationFactors<T> {
+  NormalizationFactors { scaler: Scaler::None, ..NormalizationFactors::default() }
+}
+
+fn extension<W: Write>(writer: &mut Writer<W>, name: &str, value: &str) -> io::Result<()> {
+  writer
+    .create_element("Extension")
+    .with_attributes([("extender", EXTENDER), ("name", name), ("value", value)])
+    .write_empty()?;
+  Ok(())
+}
+
+/// What the parser collected from the document.
+#[derive(Default)]
+struct Elements {
+  regression_models: Vec<HashMap<String, String>>,
+  mining_fields: Vec<HashMap<String, String>>,
+  tables: Vec<HashMap<String, String>>,
+  predictors: Vec<HashMap<String, String>>,
+  targets: Vec<HashMap<String, String>>,
+  /// Name of the first element this reader cannot evaluate
+  unsupported: Option<String>,
+  extensions: HashMap<String, String>,
+}
+
+fn parse<T: Float>(path: &str, xml: &str) -> Result<PmmlModel<T>> {
+  let corrupt = |reason: String| LinearRegressionError::CorruptParameterFile { path: path.to_string(), reason };
+  let mut reader = Reader::from_str(xml);
+  reader.config_mut().trim_text(true);
+  let mut elements = Elements::default();
+
+  loop {
+    let element = match reader.read_event().map_err(|err| corrupt(err.to_string()))? {
+      Event::Start(element) | Event::Empty(element) => element,
+      Event::Eof => break,
+      _ => continue,
+    };
+    let name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+    let attributes = attributes(&element).map_err(corrupt)?;
+    match name.as_str() {
+      "RegressionModel" => elements.regression_models.push(attributes),
+      "MiningField" => elements.mining_fields.push(attributes),
+      "RegressionTable" => elements.tables.push(attributes),
+      "NumericPredictor" => elements.predictors.push(attributes),
+      "Target" => elements.targets.push(attributes),
+      "CategoricalPredictor" | "PredictorTerm" | "TargetValue" => {
+        elements.unsupported.get_or_insert(name);
+      }
+      "Extension" if attributes.get("extender").map(String::as_str) == Some(EXTENDER) => {
+        if let (Some(name), Some(value)) = (attributes.get("name"), attributes.get("value")) {
+          elements.extensions.insert(name.clone(), value.clone());
+        }
+      }
+      _ => {}
+    }
+  }
+  build(elements).map_err(corrupt)
+}
+
+/// Writes `value` as an attribute, failing with `NonFiniteParameter` on
+/// NaN and the infinities, which PMML consumers reject.
+fn literal<V: Float>(name: &str, value: V) -> Result<String> {
+  check_finite(name, value.as_f64())?;
+  Ok(value.to_string())
+}
+
+fn attributes(element: &BytesStart) -> std::result::Result<HashMap<String, String>, String> {
+  element
+    .attributes()
+    .map(|attribute| {
+      let attribute = attribute.map_err(|err| err.to_string())?;
+      let value = attribute.normalized_value(XmlVersion::Implicit1_0).map_err(|err| err.to_string())?;
+      Ok((String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(), value.into_owned()))
+    })
+    .collect()
+}
+
+fn build<T: Float>(elements: Elements) -> std::result::Result<PmmlModel<T>, String> {
+  let number = |attributes: &HashMap<String, String>, name: &str, default: Option<f64>| {
+    match (attributes.get(name), default) {
+      (Some(value), _) => match value.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => Ok(number),
+        _ => Err(format!("{} '{}' is not a finite number", name, value)),
+      },
+      (None, Some(default)) => Ok(default),
+      (None, None) => Err(format!("{} is missing", name)),
+    }
+  };
+
+  let [model] = elements.regression_models.as_slice() else {
+    return Err(format!("expected one RegressionModel, found {}", elements.regression_models.len()));
+  };
+  if let Some(function) = model.get("functionName").filter(|function| *function != "regression") {
+    return Err(format!("functionName '{}' is not supported, expected regression", function));
+  }
+  if let Some(method) = model.get("normalizationMethod").filter(|method| *method != "none") {
+    return Err(format!("normalizationMethod '{}' is not supported", method));
+  }
+  if let Some(element) = elements.unsupported {
+    return Err(format!("{} is not supported, only a linear term of one numeric field is", element));
+  }
+  let [table] = elements.tables.as_slice() else {
+    return Err(format!("expected one RegressionTable, found {}", elements.tables.len()));
+  };
+
+  let field = |usage: &[&str]| {
+    elements.mining_fields.iter().find_map(|field| {
+      let field_usage = field.get("usageType").map_or("active", String::as_str);
+      usage.contains(&field_usage).then(|| field.get("name").cloned()).flatten()
+    })
+  };
+  let (feature, slope) = match elements.predictors.as_slice() {
+    [] => (field(&["active"]).unwrap_or_else(|| "km".to_string()), 0.0),
+    [predictor] => {
+      let name = predictor.get("name").cloned().ok_or("NumericPredictor has no name")?;
+      if number(predictor, "exponent", Some(1.0))? != 1.0 {
+        return Err(format!("predictor '{}' has an exponent, only linear terms are supported", name));
+      }
+      if !elements.mining_fields.iter().any(|field| field.get("name") == Some(&name)) {
+        return Err(format!("predictor '{}' is not a mining field, derived fields are not supported", name));
+      }
+      (name, number(predictor, "coefficient", None)?)
+    }
+    predictors => return Err(format!("expected one NumericPredictor, found {}", predictors.len())),
+  };
+  let target = field(&["target", "predicted"]).unwrap_or_else(|| "price".to_string());
+  let intercept = number(table, "intercept", None)?;
+  let clamp = match elements.targets.as_slice() {
+    [] => ClampPolicy::NONE,
+    [bounds] => {
+      if number(bounds, "rescaleFactor", Some(1.0))? != 1.0 || number(bounds, "rescaleConstant", Some(0.0))? != 0.0 {
+        return Err("Target rescaling is not supported".to_string());
+      }
+      let bound = |name| bounds.get(name).map(|_| number(bounds, name, None)).transpose();
+      ClampPolicy { floor: bound("min")?, ceiling: bound("max")? }
+    }
+    targets => return Err(format!("expected at most one Target, found {}", targets.len())),
+  };
+
+  let normalization: NormalizationFactors<T> = match elements.extensions.get("normalization") {
+    Some(json) => serde_json::from_str(json).map_err(|err| format!("normalization extension: {}", err))?,
+    None => raw_units(),
+  };
+  let imputation: Option<Imputer> = match elements.extensions.get("imputation") {
+    Some(json) => Some(serde_json::from_str(json).map_err(|err| format!("imputation extension: {}", err))?),
+    None => None,
+  };
+  let distribution: Option<FeatureDistribution> = match elements.extensions.get("distribution") {
+    Some(json) => Some(serde_json::from_str(json).map_err(|err| format!("distribution extension: {}", err))?),
+    None => None,
+  };
+
+  let mut model = LinearRegression::builder().scaler(normalization.scaler).clamp(clamp).build();
+  let (theta0, theta1) = reexpress_params((T::of(intercept), T::of(slope)), &raw_units(), &normalization);
+  model.set_normalization(normalization);
+  model.set_params(theta0, theta1);
+  model.set_imputation(imputation);
+  model.set_feature_distribution(distribution);
+  Ok(PmmlModel { model, feature, target })
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::float::Float;
+
+/// Bounds a model's predictions are kept within, e.g. so a price is never
+/// negative. Either bound may be left open.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ClampPolicy {
+  pub floor: Option<f64>,
+  pub ceiling: Option<f64>,
+}
+
+impl ClampPolicy {
+  /// Predictions are returned as computed
+  pub const NONE: ClampPolicy = ClampPolicy { floor: None, ceiling: None };
+
+  /// Predictions below zero are raised to zero
+  pub const NON_NEGATIVE: ClampPolicy = ClampPolicy { floor: Some(0.0), ceiling: None };
+
+  /// Whether any bound is set
+  pub fn is_none(&self) -> bool {
+    self.floor.is_none() && self.ceiling.is_none()
+  }
+
+  /// Brings `value` within the bounds
+  pub fn apply<T: Float>(&self, value: T) -> T {
+    let value = self.floor.map_or(value, |floor| value.max(T::of(floor)));
+    self.ceiling.map_or(value, |ceiling| value.min(T::of(ceiling)))
+  }
+}
+
+/// Parses `none`, `non-negative`, or `floor=<price>` and `ceiling=<price>`
+/// separated by a comma, e.g. `floor=500,ceiling=20000`.
+impl FromStr for ClampPolicy {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_ascii_lowercase().as_str() {
+      "" | "none" => return Ok(ClampPolicy::NONE),
+      "non-negative" | "nonnegative" => return Ok(ClampPolicy::NON_NEGATIVE),
+      _ => {}
+    }
+
+    let mut policy = ClampPolicy::NONE;
+    for bound in s.split(',') {
+      let (name, value) = bound.split_once('=').ok_or_else(|| {
+        format!("Unknown clamp '{}', expected none, non-negative, floor=<price> or ceiling=<price>", bound.trim())
+      })?;
+      let value: f64 = value.trim().parse().map_err(|_| format!("'{}' is not a number", value.trim()))?;
+      if !value.is_finite() {
+        return Err(format!("{} must be finite", name.trim()));
+      }
+      match name.trim().to_ascii_lowercase().as_str() {
+        "floor" => policy.floor = Some(value),
+        "ceiling" => policy.ceiling = Some(value),
+        other => return Err(format!("Unknown bound '{}', expected floor or ceiling", other)),
+      }
+    }
+    if let (Some(floor), Some(ceiling)) = (policy.floor, policy.ceiling) {
+      if floor > ceiling {
+        return Err(format!("floor {} is above ceiling {}", floor, ceiling));
+      }
+    }
+    Ok(policy)
+  }
+}
+
+impl fmt::Display for ClampPolicy {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match (self.floor, self.ceiling) {
+      (None, None) => write!(f, "none"),
+      (Some(0.0), None) => write!(f, "non-negative"),
+      (Some(floor), None) => write!(f, "floor={}", floor),
+      (None, Some(ceiling)) => write!(f, "ceiling={}", ceiling),
+      (Some(floor), Some(ceiling)) => write!(f, "floor={},ceiling={}", floor, ceiling),
+    }
+  }
+}
+
+/// Where an input lies outside the mileages the model was trained on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Extrapolation<T: Float = f64> {
+  /// Below the lowest mileage trained on, `min`
+  Below { min: T },
+  /// Above the highest mileage trained on, `max`
+  Above { max: T },
+}
+
+impl<T: Float> fmt::Display for Extrapolation<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Extrapolation::Below { min } => write!(f, "below the lowest mileage trained on ({:.2} km)", min),
+      Extrapolation::Above { max } => write!(f, "above the highest mileage trained on ({:.2} km)", max),
+    }
+  }
+}
+
+/// A price predicted by `LinearRegression::predict`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Prediction<T: Float = f64> {
+  /// The price, within the model's `ClampPolicy`
+  pub value: T,
+  /// The price on the fitted line, before clamping
+  pub unclamped: T,
+  /// Set when the mileage is outside the training range, where the fitted
+  /// line is less trustworthy. `None` when the range is unknown.
+  pub extrapolation: Option<Extrapolation<T>>,
+}
+
+impl<T: Float> Prediction<T> {
+  /// Whether the clamp policy changed the price. A NaN left as it is
+  /// was not clamped.
+  pub fn is_clamped(&self) -> bool {
+    self.value != self.unclamped && !(self.value.is_nan() && self.unclamped.is_nan())
+  }
+
+  /// Whether the mileage is outside the training range
+  pub fn is_extrapolated(&self) -> bool {
+    self.extrapolation.is_some()
+  }
+}
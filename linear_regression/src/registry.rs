@@ -0,0 +1,328 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use crate::dataset::DatasetFingerprint;
+use crate::error::{LinearRegressionError, Result};
+use crate::float::{Float, Precision};
+use crate::model_file::{Metrics, ModelFile, TrainingConfig};
+use crate::persistence::{read_locked, write_atomically};
+
+/// Tag that marks the model serving predictions.
+pub const PRODUCTION_TAG: &str = "production";
+
+/// Reference to the most recently registered model.
+pub const LATEST: &str = "latest";
+
+/// File in the registry directory listing its models and tags.
+const INDEX_FILE: &str = "index.json";
+
+/// What the registry records about a model, so it can be listed without
+/// reading every model file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelEntry {
+  pub id: String,
+  pub created_at: DateTime<Utc>,
+  pub precision: Precision,
+  pub training: TrainingConfig,
+  pub metrics: Option<Metrics>,
+  pub dataset: Option<DatasetFingerprint>,
+}
+
+impl fmt::Display for ModelEntry {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}  {}  {}", self.id, self.created_at.format("%Y-%m-%d %H:%M:%S"), self.precision)?;
+    match &self.metrics {
+      Some(metrics) => write!(f, "  R² {:.4}  RMSE {:.2}", metrics.r2, metrics.rmse)?,
+      None => write!(f, "  no metrics")?,
+    }
+    if let Some(dataset) = &self.dataset {
+      write!(f, "  {} rows", dataset.rows)?;
+    }
+    Ok(())
+  }
+}
+
+/// Contents of the index file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RegistryIndex {
+  /// Registered models, oldest first
+  models: Vec<ModelEntry>,
+  /// Ids each tag has pointed to, the current one last
+  tags: BTreeMap<String, Vec<String>>,
+}
+
+/// A field that differs between two models.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+  /// Dotted path of the field in the model file, e.g. `metrics.r2`
+  pub field: String,
+  /// `None` when the field is missing from that model
+  pub left: Option<Value>,
+  pub right: Option<Value>,
+}
+
+/// The fields that differ between two registered models.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelDiff {
+  pub left: String,
+  pub right: String,
+  pub changes: Vec<Change>,
+}
+
+impl fmt::Display for ModelDiff {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} → {}", self.left, self.right)?;
+    if self.changes.is_empty() {
+      return write!(f, "\n  identical");
+    }
+    let show = |value: &Option<Value>| value.as_ref().map_or("(none)".to_string(), Value::to_string);
+    for change in &self.changes {
+      write!(f, "\n  {}: {} → {}", change.field, show(&change.left), show(&change.right))?;
+    }
+    Ok(())
+  }
+}
+
+/// A directory of model files, one per training run, with an index
+/// recording their metrics and the tags pointing at them.
+///
+/// Models are referred to by tag, by id, by a prefix of a single id, or
+/// as `latest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registry {
+  root: String,
+}
+
+impl Registry {
+  /// Opens the registry in the directory `root`, creating it if needed.
+  pub fn open(root: &str) -> Result<Self> {
+    fs::create_dir_all(root)?;
+    Ok(Self { root: root.to_string() })
+  }
+
+  /// Directory holding the registry
+  pub fn root(&self) -> &str {
+    &self.root
+  }
+
+  /// Stores `model` under a new id and records it in the index.
+  pub fn register<T: Float>(&self, model: &ModelFile<T>) -> Result<ModelEntry> {
+    let hash = Sha256::digest(serde_json::to_vec(model).expect("models serialize to JSON"));
+    let short: String = hash.iter().take(4).map(|byte| format!("{:02x}", byte)).collect();
+    let entry = ModelEntry {
+      id: format!("{}-{}", model.created_at.format("%Y%m%d-%H%M%S"), short),
+      created_at: model.created_at,
+      precision: model.precision,
+      training: model.training,
+      metrics: model.metrics,
+      dataset: model.dataset.clone(),
+    };
+
+    model.clone().save(&self.model_path(&entry))?;
+    self.update_index(|index| {
+      index.models.retain(|registered| registered.id != entry.id);
+      index.models.push(entry.clone());
+      Ok(())
+    })?;
+    Ok(entry)
+  }
+
+  /// Every registered model, oldest first
+  pub fn entries(&self) -> Result<Vec<ModelEntry>> {
+    Ok(self.read_index()?.models)
+  }
+
+  /// Each tag and the id it currently points to
+  pub fn tags(&self) -> Result<BTreeMap<String, String>> {
+    Ok(
+      self.read_index()?
+        .tags
+        .into_iter()
+        .filter_map(|(tag, ids)| Some((tag, ids.last()?.clone())))
+        .collect(),
+    )
+  }
+
+  /// Tags currently pointing at the model `id`
+  pub fn tags_of(&self, id: &str) -> Result<Vec<String>> {
+    Ok(self.tags()?.into_iter().filter(|(_, tagged)| tagged == id).map(|(tag, _)| tag).collect())
+  }
+
+  /// Finds the model `reference` names: a tag, an id, a prefix of exactly
+  /// one id, or `latest`.
+  pub fn resolve(&self, reference: &str) -> Result<ModelEntry> {
+    resolve_in(&self.read_index()?, reference).map_err(|message| self.error(message))
+  }
+
+  /// Where the model file of `entry` is stored
+  pub fn model_path(&self, entry: &ModelEntry) -> String {
+    Path::new(&self.root).join(format!("{}.json", entry.id)).to_string_lossy().into_owned()
+  }
+
+  /// Loads the model `reference` names, in the precision `T`.
+  pub fn load<T: Float>(&self, reference: &str) -> Result<ModelFile<T>> {
+    ModelFile::load(&self.model_path(&self.resolve(reference)?))
+  }
+
+  /// Loads the model `reference` names in whatever precision it was saved
+  /// in, converted to `T`.
+  pub fn load_as<T: Float>(&self, reference: &str) -> Result<ModelFile<T>> {
+    let entry = self.resolve(reference)?;
+    let path = self.model_path(&entry);
+    Ok(match entry.precision {
+      Precision::F32 => ModelFile::<f32>::load(&path)?.cast(),
+      Precision::F64 => ModelFile::<f64>::load(&path)?.cast(),
+    })
+  }
+
+  /// The model file of `entry`, as JSON
+  pub fn model_json(&self, entry: &ModelEntry) -> Result<Value> {
+    let path = self.model_path(entry);
+    let contents = read_locked(&path).map_err(|err| match err.kind() {
+      io::ErrorKind::NotFound => LinearRegressionError::ModelNotFound { path: path.clone() },
+      _ => err.into(),
+    })?;
+    serde_json::from_str(&contents).map_err(|err| LinearRegressionError::json(&path, err))
+  }
+
+  /// Points `tag` at the model `reference` names. The model it pointed at
+  /// before is remembered, so the tag can be rolled back.
+  pub fn tag(&self, tag: &str, reference: &str) -> Result<ModelEntry> {
+    let tag = tag.trim();
+    if tag.is_empty() || tag == LATEST {
+      return Err(self.error(format!("'{}' cannot be used as a tag", tag)));
+    }
+    self.update_index(|index| {
+      let entry = resolve_in(index, reference).map_err(|message| self.error(message))?;
+      if index.models.iter().any(|model| model.id == tag) {
+        return Err(self.error(format!("'{}' is a model id and cannot be used as a tag", tag)));
+      }
+      let ids = index.tags.entry(tag.to_string()).or_default();
+      if ids.last() != Some(&entry.id) {
+        ids.push(entry.id.clone());
+      }
+      Ok(entry)
+    })
+  }
+
+  /// Tags the model `reference` names as `production`.
+  pub fn promote(&self, reference: &str) -> Result<ModelEntry> {
+    self.tag(PRODUCTION_TAG, reference)
+  }
+
+  /// Points `tag` back at the model it pointed at before its last change,
+  /// and returns that model.
+  pub fn rollback(&self, tag: &str) -> Result<ModelEntry> {
+    self.update_index(|index| {
+      let ids = index.tags.get_mut(tag).filter(|ids| ids.len() > 1).ok_or_else(|| {
+        self.error(format!("tag '{}' has no earlier model to roll back to", tag))
+      })?;
+      ids.pop();
+      let previous = ids.last().cloned().unwrap_or_default();
+      resolve_in(index, &previous).map_err(|message| self.error(message))
+    })
+  }
+
+  /// Compares the model files of two models, field by field.
+  pub fn diff(&self, left: &str, right: &str) -> Result<ModelDiff> {
+    let (left, right) = (self.resolve(left)?, self.resolve(right)?);
+    let mut fields = (BTreeMap::new(), BTreeMap::new());
+    flatten("", self.model_json(&left)?, &mut fields.0);
+    flatten("", self.model_json(&right)?, &mut fields.1);
+
+    let mut names: Vec<&String> = fields.0.keys().chain(fields.1.keys()).collect();
+    names.sort();
+    names.dedup();
+    let changes = names
+      .into_iter()
+      .filter(|name| fields.0.get(*name) != fields.1.get(*name))
+      .map(|name| Change {
+        field: name.clone(),
+        left: fields.0.get(name).cloned(),
+        right: fields.1.get(name).cloned(),
+      })
+      .collect();
+    Ok(ModelDiff { left: left.id, right: right.id, changes })
+  }
+
+  fn index_path(&self) -> String {
+    Path::new(&self.root).join(INDEX_FILE).to_string_lossy().into_owned()
+  }
+
+  fn read_index(&self) -> Result<RegistryIndex> {
+    parse_index(&self.index_path(), read_locked(&self.index_path()))
+  }
+
+  /// Reads, changes and rewrites the index while holding its lock, so
+  /// concurrent trainers don't lose each other's models.
+  fn update_index<R>(&self, change: impl FnOnce(&mut RegistryIndex) -> Result<R>) -> Result<R> {
+    let path = self.index_path();
+    let mut result = None;
+    write_atomically(&path, |writer| {
+      // The lock is already held, so read the file directly
+      let mut index = parse_index(&path, fs::read_to_string(&path))?;
+      result = Some(change(&mut index)?);
+      serde_json::to_writer_pretty(&mut *writer, &index).map_err(|err| LinearRegressionError::json(&path, err))?;
+      writeln!(writer)?;
+      Ok(())
+    })?;
+    Ok(result.expect("the index was updated"))
+  }
+
+  fn error(&self, message: String) -> LinearRegressionError {
+    LinearRegressionError::Registry { path: self.root.clone(), message }
+  }
+}
+
+fn parse_index(path: &str, contents: io::Result<String>) -> Result<RegistryIndex> {
+  match contents {
+    Ok(contents) => serde_json::from_str(&contents).map_err(|err| LinearRegressionError::CorruptParameterFile {
+      path: path.to_string(),
+      reason: err.to_string(),
+    }),
+    Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(RegistryIndex::default()),
+    Err(err) => Err(err.into()),
+  }
+}
+
+fn resolve_in(index: &RegistryIndex, reference: &str) -> std::result::Result<ModelEntry, String> {
+  let reference = reference.trim();
+  let find = |id: &str| index.models.iter().find(|model| model.id == id);
+
+  if reference == LATEST {
+    return index.models.last().cloned().ok_or_else(|| "no model has been registered".to_string());
+  }
+  if let Some(id) = index.tags.get(reference).and_then(|ids| ids.last()) {
+    return find(id).cloned().ok_or_else(|| format!("tag '{}' points at unknown model '{}'", reference, id));
+  }
+  if let Some(entry) = find(reference) {
+    return Ok(entry.clone());
+  }
+  let matches: Vec<&ModelEntry> = index.models.iter().filter(|model| model.id.starts_with(reference)).collect();
+  match matches.as_slice() {
+    [entry] if !reference.is_empty() => Ok((*entry).clone()),
+    [] | [_] => Err(format!("no model or tag named '{}'", reference)),
+    _ => Err(format!("'{}' matches {} models, give more of the id", reference, matches.len())),
+  }
+}
+
+/// Collects the leaves of `value` under their dotted paths.
+fn flatten(prefix: &str, value: Value, fields: &mut BTreeMap<String, Value>) {
+  match value {
+    Value::Object(object) => {
+      for (key, value) in object {
+        let path = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+        flatten(&path, value, fields);
+      }
+    }
+    value => {
+      fields.insert(prefix.to_string(), value);
+    }
+  }
+}
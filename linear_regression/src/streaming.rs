@@ -0,0 +1,181 @@
+use crate::error::{LinearRegressionError, Result};
+use std::marker::PhantomData;
+use crate::columns::Columns;
+use crate::dataset::{DatasetFingerprint, DatasetSource, Fingerprinter};
+use crate::drift::DistributionSketch;
+use crate::float::Float;
+use crate::linear_regression::{check_cost, LinearRegression};
+use crate::normalization::NormalizationFactors;
+use crate::observer::{NoopObserver, TrainingControl, TrainingObserver};
+use crate::utils::{for_each_chunk, get_dataset_path, get_load_options, normalize_dataset};
+use crate::parsing::{LoadOptions, LoadReport};
+
+/// Trains a model straight from a dataset file without loading it into memory.
+///
+/// A first pass over the file computes the normalization factors; each
+/// epoch then re-reads the file in chunks, accumulates the full-batch
+/// gradient and takes one gradient descent step. Memory use is bounded by
+/// `chunk_size` rows.
+pub struct StreamingTrainer<T: Float = f64> {
+  path: String,
+  chunk_size: usize,
+  options: LoadOptions,
+  report: LoadReport,
+  _precision: PhantomData<T>,
+}
+
+impl<T: Float> StreamingTrainer<T> {
+  /// Creates a trainer over the dataset file at `path`.
+  pub fn new(path: impl Into<String>, chunk_size: usize) -> Self {
+    Self {
+      path: path.into(),
+      chunk_size: chunk_size.max(1),
+      options: LoadOptions::default(),
+      report: LoadReport::default(),
+      _precision: PhantomData,
+    }
+  }
+
+  /// Creates a trainer over the dataset configured by `DATASET_PATH`,
+  /// read as described by `get_load_options`.
+  pub fn from_env(chunk_size: usize) -> Result<Self> {
+    Ok(Self::new(get_dataset_path()?, chunk_size).with_options(get_load_options()?))
+  }
+
+  /// Sets how the file is read.
+  pub fn with_options(mut self, options: LoadOptions) -> Self {
+    self.options = options;
+    self
+  }
+
+  /// The file the trainer reads from.
+  pub fn source(&self) -> DatasetSource {
+    DatasetSource::File(self.path.clone())
+  }
+
+  /// Number of rows found by the normalization pass.
+  pub fn rows(&self) -> usize {
+    self.report.rows
+  }
+
+  /// Rows read and dropped by the normalization pass.
+  pub fn load_report(&self) -> &LoadReport {
+    &self.report
+  }
+
+  /// Fingerprints the rows the trainer reads: complete rows only, neither
+  /// imputed nor validated. Matches the fingerprint `init` installs.
+  pub fn fingerprint(&self) -> Result<DatasetFingerprint> {
+    let mut fingerprinter = Fingerprinter::default();
+    for_each_chunk(&self.path, self.chunk_size, &self.options, |chunk: &[(T, T)]| fingerprinter.update(chunk))?;
+    Ok(DatasetFingerprint { streamed: true, ..fingerprinter.finish(self.source()) })
+  }
+
+  /// First pass: scans the file for normalization factors (using the
+  /// model's scaler) and installs them on `model`, with the fingerprint
+  /// of the rows read. A second pass, once the mileage range is known,
+  /// sketches the feature distribution.
+  pub fn init(&mut self, model: &mut LinearRegression<T>) -> Result<()> {
+    let scaler = model.get_normalization().scaler;
+    let mut factors: Option<NormalizationFactors<T>> = None;
+    let mut fingerprinter = Fingerprinter::default();
+
+    self.report = for_each_chunk(&self.path, self.chunk_size, &self.options, |chunk: &[(T, T)]| {
+      fingerprinter.update(chunk);
+      factors = Some(match &factors {
+        Some(factors) => factors.extended(chunk),
+        None => NormalizationFactors::fit(chunk, scaler),
+      });
+    })?;
+
+    let factors = factors.ok_or(LinearRegressionError::EmptyDataset)?;
+    factors.check_feature_range()?;
+    let mut sketch = DistributionSketch::new(factors.x_min.as_f64(), factors.x_max.as_f64());
+    for_each_chunk(&self.path, self.chunk_size, &self.options, |chunk: &[(T, T)]| {
+      sketch.update(chunk.iter().map(|&(x, _)| x));
+    })?;
+
+    model.set_normalization(factors);
+    model.set_dataset_fingerprint(Some(DatasetFingerprint { streamed: true, ..fingerprinter.finish(self.source()) }));
+    model.set_feature_distribution(sketch.finish());
+    Ok(())
+  }
+
+  /// Runs one epoch over the file and takes a gradient step.
+  /// Returns the cost at the start of the epoch.
+  pub fn epoch(&self, model: &mut LinearRegression<T>) -> Result<T> {
+    let (cost, gradient) = self.evaluate(model)?;
+    check_cost(model.get_history().len(), cost)?;
+    let step = model.step_snapshot(model.get_history().len(), cost, gradient);
+    model.apply_step(&step);
+    Ok(cost)
+  }
+
+  /// Runs up to `epochs` epochs, stopping early on convergence like
+  /// `LinearRegression::train`. Returns the number of steps taken.
+  pub fn train(&self, model: &mut LinearRegression<T>, epochs: usize) -> Result<usize> {
+    self.train_with(model, epochs, &mut NoopObserver)
+  }
+
+  /// Like `train`, reporting every epoch to `observer`.
+  pub fn train_with<O>(
+    &self,
+    model: &mut LinearRegression<T>,
+    epochs: usize,
+    observer: &mut O,
+  ) -> Result<usize>
+  where
+    O: TrainingObserver<T> + ?Sized,
+  {
+    let steps = self.descend(model, epochs, observer)?;
+    model.set_trained(true);
+    Ok(steps)
+  }
+
+  /// Runs the epochs of `train_with`.
+  fn descend<O>(&self, model: &mut LinearRegression<T>, epochs: usize, observer: &mut O) -> Result<usize>
+  where
+    O: TrainingObserver<T> + ?Sized,
+  {
+    for epoch in 0..epochs {
+      let (cost, gradient) = self.evaluate(model)?;
+      check_cost(epoch, cost)?;
+      if model.should_stop(epoch, cost, gradient) {
+        return Ok(epoch);
+      }
+
+      let step = model.step_snapshot(epoch, cost, gradient);
+      model.apply_step(&step);
+
+      if observer.on_step(&step) == TrainingControl::Stop {
+        return Ok(epoch + 1);
+      }
+    }
+    Ok(epochs)
+  }
+
+  /// Streams the file once, returning the model's cost and gradient over it.
+  fn evaluate(&self, model: &LinearRegression<T>) -> Result<(T, (T, T))> {
+    let (theta0, theta1) = model.get_params();
+    let loss = model.get_loss();
+    let mut loss_sum = T::zero();
+    let mut gradient_sums = (T::zero(), T::zero());
+
+    let rows = for_each_chunk(&self.path, self.chunk_size, &self.options, |chunk: &[(T, T)]| {
+      let batch = Columns::from_pairs(&normalize_dataset(chunk, model.get_normalization()));
+      let (sum_theta0, sum_theta1) = batch.gradient_sum(theta0, theta1, loss);
+      loss_sum += batch.loss_sum(theta0, theta1, loss);
+      gradient_sums.0 += sum_theta0;
+      gradient_sums.1 += sum_theta1;
+    })?.rows;
+
+    if rows == 0 {
+      return Err(LinearRegressionError::EmptyDataset);
+    }
+
+    Ok((
+      model.cost_from_sum(loss_sum, rows, theta1),
+      model.gradient_from_sums(gradient_sums, rows, theta1),
+    ))
+  }
+}
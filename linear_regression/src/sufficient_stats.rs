@@ -0,0 +1,78 @@
+use serde::{Serialize, Deserialize};
+use crate::float::Float;
+use crate::columns::Columns;
+
+/// Running sums that fully determine the squared-loss cost and gradient of
+/// a single-feature linear model, so they can be evaluated in O(1).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "T: Float")]
+pub struct SufficientStats<T: Float = f64> {
+  pub n: usize,
+  pub sum_x: T,
+  pub sum_y: T,
+  pub sum_xy: T,
+  pub sum_xx: T,
+  pub sum_yy: T,
+}
+
+impl<T: Float> SufficientStats<T> {
+  /// Accumulates the statistics of a dataset in one pass
+  pub fn from_columns(data: &Columns<T>) -> Self {
+    let mut stats = Self::default();
+    stats.add(data);
+    stats
+  }
+
+  /// Adds the samples of `data` to the running sums
+  pub fn add(&mut self, data: &Columns<T>) {
+    for (x, y) in data.iter() {
+      self.sum_x += x;
+      self.sum_y += y;
+      self.sum_xy += x * y;
+      self.sum_xx += x * x;
+      self.sum_yy += y * y;
+    }
+    self.n += data.len();
+  }
+
+  /// The statistics of the same samples mapped by x ↦ a·x + b and
+  /// y ↦ c·y + d, e.g. when they are normalized with new factors
+  pub fn affine(&self, (a, b): (T, T), (c, d): (T, T)) -> Self {
+    let n = T::of(self.n as f64);
+    let two = T::of(2.0);
+
+    Self {
+      n: self.n,
+      sum_x: a * self.sum_x + n * b,
+      sum_y: c * self.sum_y + n * d,
+      sum_xy: a * c * self.sum_xy + a * d * self.sum_x + b * c * self.sum_y + n * b * d,
+      sum_xx: a * a * self.sum_xx + two * a * b * self.sum_x + n * b * b,
+      sum_yy: c * c * self.sum_yy + two * c * d * self.sum_y + n * d * d,
+    }
+  }
+
+  /// Σ ½(θ₀ + θ₁·x − y)², expanded in terms of the sums
+  pub fn squared_loss_sum(&self, theta0: T, theta1: T) -> T {
+    let n = T::of(self.n as f64);
+    let two = T::of(2.0);
+
+    T::of(0.5) * (
+      n * theta0 * theta0
+        + theta1 * theta1 * self.sum_xx
+        + self.sum_yy
+        + two * theta0 * theta1 * self.sum_x
+        - two * theta0 * self.sum_y
+        - two * theta1 * self.sum_xy
+    )
+  }
+
+  /// (Σ e, Σ e·x) with e = θ₀ + θ₁·x − y
+  pub fn squared_gradient_sum(&self, theta0: T, theta1: T) -> (T, T) {
+    let n = T::of(self.n as f64);
+
+    (
+      n * theta0 + theta1 * self.sum_x - self.sum_y,
+      theta0 * self.sum_x + theta1 * self.sum_xx - self.sum_xy,
+    )
+  }
+}
@@ -1,107 +1,493 @@
-use std::error::Error;
-use std::path::Path;
 use std::env;
-use std::fs::File;
-use std::io::{self, BufRead, Write};
+use std::fs::{self, File};
+use std::io;
 use dotenv::dotenv;
-use csv::ReaderBuilder;
 use crate::normalization::NormalizationFactors;
+use crate::float::{Float, Precision};
+use crate::linear_regression::LinearRegression;
+use crate::error::{LinearRegressionError, Result};
+use crate::dataset::{Dataset, DatasetFingerprint, DatasetMatch, Fallback};
+use crate::parsing::{DroppedRow, LoadOptions, LoadReport};
+use crate::imputation::{complete_rows, Imputer};
+use crate::formats::for_each_row;
+use crate::validation::ValidationRules;
+use crate::model_file::{model_precision, Metrics, ModelFile};
+use crate::persistence::read_locked;
+use crate::prediction::ClampPolicy;
+use crate::streaming::StreamingTrainer;
 
 /// Retrieves the dataset path from the `.env` file.
-pub fn get_dataset_path() -> Result<String, Box<dyn Error>> {
+pub fn get_dataset_path() -> Result<String> {
   dotenv().ok();
 
-  env::var("DATASET_PATH").or_else(|_| Ok("./data/data.csv".to_string()))
+  Ok(env::var("DATASET_PATH").unwrap_or("./data/data.csv".to_string()))
 }
 
 /// Loads and parses the dataset using the `csv` library, skipping the header row.
-pub fn load_dataset_file() -> Result<Vec<(f64, f64)>, Box<dyn Error>> {
-  let path = get_dataset_path()?;
+pub fn load_dataset_file<T: Float>() -> Result<Vec<(T, T)>> {
+  load_dataset_from(&get_dataset_path()?)
+}
+
+/// Loads and parses the dataset file at `path`, skipping the header row.
+pub fn load_dataset_from<T: Float>(path: &str) -> Result<Vec<(T, T)>> {
+  Ok(read_dataset_file(path, &LoadOptions::default())?.records)
+}
+
+/// The contents of a dataset file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetFile<T: Float = f64> {
+  /// Header of the feature and target columns, empty without a header row
+  pub headers: (String, String),
+  pub records: Vec<(T, T)>,
+  /// Line of the file each record was read from
+  pub lines: Vec<usize>,
+  pub report: LoadReport,
+  /// Fill values used for missing cells, when they are imputed
+  pub imputation: Option<Imputer>,
+}
+
+/// Reads the selected columns of the dataset file at `path`, along with
+/// their header and the line each record came from. Missing cells are
+/// handled as `options.missing` says.
+pub fn read_dataset_file<T: Float>(path: &str, options: &LoadOptions) -> Result<DatasetFile<T>> {
+  let mut rows = Vec::new();
+  let (headers, mut report) = for_each_row(path, options, |line, row| rows.push((line, row)))?;
+
+  let names = (
+    if headers.0.is_empty() { "km" } else { &headers.0 },
+    if headers.1.is_empty() { "price" } else { &headers.1 },
+  );
+  let (rows, imputation) = complete_rows(options.missing, rows, names, &mut report);
+  let (lines, records) = rows.into_iter().unzip();
+  Ok(DatasetFile { headers, records, lines, report, imputation })
+}
 
-  let mut reader = ReaderBuilder::new().has_headers(true).from_path(Path::new(&path))?;
+/// Rows read at a time when fingerprinting a dataset as it is streamed
+const FINGERPRINT_CHUNK_SIZE: usize = 4096;
 
-  let mut dataset = Vec::new();
-  for result in reader.records() {
-    let record = result?;
-    if let (Some(km), Some(price)) = (record.get(0), record.get(1)) {
-      dataset.push((km.parse()?, price.parse()?));
+/// Streams a dataset file in chunks of at most `chunk_size` rows, so memory
+/// stays bounded regardless of the file size (except for JSON arrays, which
+/// are parsed whole). Imputation needs the whole file, so rows with missing
+/// cells are dropped. Returns what was read and dropped.
+pub fn for_each_chunk<T, F>(path: &str, chunk_size: usize, options: &LoadOptions, mut f: F) -> Result<LoadReport>
+where
+  T: Float,
+  F: FnMut(&[(T, T)]),
+{
+  let chunk_size = chunk_size.max(1);
+  let mut chunk = Vec::with_capacity(chunk_size);
+  let mut incomplete = Vec::new();
+  let (_, mut report) = for_each_row(path, options, |line, row| {
+    let (Some(x), Some(y)) = row else {
+      incomplete.push(line);
+      return;
+    };
+    chunk.push((x, y));
+    if chunk.len() == chunk_size {
+      f(&chunk);
+      chunk.clear();
     }
+  })?;
+  if !chunk.is_empty() {
+    f(&chunk);
+  }
+
+  report.rows -= incomplete.len();
+  for line in incomplete {
+    report.dropped.push(DroppedRow { line, column: None, reason: "missing value".to_string() });
   }
+  Ok(report)
+}
 
-  Ok(dataset)
-}
-
-/// Loads the dataset and falls back to default values
-pub fn load_dataset() -> Vec<(f64, f64)> {
-  load_dataset_file().unwrap_or_else(|_| {
-        vec![
-            (240000.0, 3650.0),
-            (139800.0, 3800.0),
-            (150500.0, 4400.0),
-            (185530.0, 4450.0),
-            (176000.0, 5250.0),
-            (114800.0, 5350.0),
-            (166800.0, 5800.0),
-            (89000.0, 5990.0),
-            (144500.0, 5999.0),
-            (84000.0, 6200.0),
-            (82029.0, 6390.0),
-            (63060.0, 6390.0),
-            (74000.0, 6600.0),
-            (97500.0, 6800.0),
-            (67000.0, 6800.0),
-            (76025.0, 6900.0),
-            (48235.0, 6900.0),
-            (93000.0, 6990.0),
-            (60949.0, 7490.0),
-            (65674.0, 7555.0),
-            (54000.0, 7990.0),
-            (68500.0, 7990.0),
-            (22899.0, 7990.0),
-            (61789.0, 8290.0),
-        ]
-    })
+/// Opens the dataset file at `path`, reporting a missing file as `DatasetNotFound`.
+pub(crate) fn open_dataset_file(path: &str) -> Result<File> {
+  File::open(path).map_err(|err| match err.kind() {
+    io::ErrorKind::NotFound => LinearRegressionError::DatasetNotFound { path: path.to_string() },
+    _ => err.into(),
+  })
+}
+
+/// The sample dataset bundled with the crate
+const EMBEDDED_SAMPLE: [(f64, f64); 24] = [
+  (240000.0, 3650.0),
+  (139800.0, 3800.0),
+  (150500.0, 4400.0),
+  (185530.0, 4450.0),
+  (176000.0, 5250.0),
+  (114800.0, 5350.0),
+  (166800.0, 5800.0),
+  (89000.0, 5990.0),
+  (144500.0, 5999.0),
+  (84000.0, 6200.0),
+  (82029.0, 6390.0),
+  (63060.0, 6390.0),
+  (74000.0, 6600.0),
+  (97500.0, 6800.0),
+  (67000.0, 6800.0),
+  (76025.0, 6900.0),
+  (48235.0, 6900.0),
+  (93000.0, 6990.0),
+  (60949.0, 7490.0),
+  (65674.0, 7555.0),
+  (54000.0, 7990.0),
+  (68500.0, 7990.0),
+  (22899.0, 7990.0),
+  (61789.0, 8290.0),
+];
+
+/// Returns the sample dataset bundled with the crate.
+pub fn embedded_sample<T: Float>() -> Vec<(T, T)> {
+  EMBEDDED_SAMPLE
+    .iter()
+    .map(|&(km, price)| (T::of(km), T::of(price)))
+    .collect()
+}
+
+/// Retrieves what to do when the dataset file is missing from the `.env` file.
+/// Defaults to `strict`.
+pub fn get_dataset_fallback() -> Result<Fallback> {
+  dotenv().ok();
+
+  match env::var("DATASET_FALLBACK") {
+    Ok(value) => value.parse().map_err(|message| LinearRegressionError::InvalidConfig {
+      variable: "DATASET_FALLBACK".to_string(),
+      message,
+    }),
+    Err(_) => Ok(Fallback::default()),
+  }
 }
 
 /// Normalizes both `km` and `price`.
-pub fn normalize_dataset(data: &[(f64, f64)], factors: &NormalizationFactors) -> Vec<(f64, f64)> {
+pub fn normalize_dataset<T: Float>(data: &[(T, T)], factors: &NormalizationFactors<T>) -> Vec<(T, T)> {
   data.iter()
-    .map(|&(x, y)| (
-      (x - factors.x_min) / (factors.x_max - factors.x_min),
-      (y - factors.y_min) / (factors.y_max - factors.y_min)
-    ))
+    .map(|&(x, y)| (factors.normalize_x(x), factors.normalize_y(y)))
     .collect()
 }
 
+/// Retrieves how the dataset file is read from the `.env` file:
+/// `DATASET_FEATURE` and `DATASET_TARGET` (header name or 0-based index),
+/// `DATASET_DELIMITER`, `DATASET_QUOTE`, `DATASET_COMMENT`,
+/// `DATASET_THOUSANDS_SEPARATOR`, `DATASET_BAD_ROWS` (`fail` or `skip`)
+/// and `DATASET_FORMAT` (otherwise detected from the extension).
+pub fn get_load_options() -> Result<LoadOptions> {
+  dotenv().ok();
+  let mut options = LoadOptions::default();
+
+  if let Some(feature) = env_setting("DATASET_FEATURE", str::parse)? {
+    options.feature = feature;
+  }
+  if let Some(target) = env_setting("DATASET_TARGET", str::parse)? {
+    options.target = target;
+  }
+  if let Some(delimiter) = env_setting("DATASET_DELIMITER", parse_byte)? {
+    options.delimiter = delimiter;
+  }
+  if let Some(quote) = env_setting("DATASET_QUOTE", parse_byte)? {
+    options.quote = quote;
+  }
+  options.comment = env_setting("DATASET_COMMENT", parse_byte)?;
+  options.thousands_separator = env_setting("DATASET_THOUSANDS_SEPARATOR", parse_char)?;
+  if let Some(bad_rows) = env_setting("DATASET_BAD_ROWS", str::parse)? {
+    options.bad_rows = bad_rows;
+  }
+  if let Some(missing) = env_setting("DATASET_MISSING", str::parse)? {
+    options.missing = missing;
+  }
+  options.format = env_setting("DATASET_FORMAT", str::parse)?;
+  Ok(options)
+}
+
+/// Reads the validation rules from the environment, starting from the
+/// defaults: `VALIDATION_OUTLIERS` (`iqr`, `iqr:<k>`, `zscore` or
+/// `zscore:<threshold>`), `VALIDATION_OUTLIER_ACTION` and
+/// `VALIDATION_DUPLICATES` (`ignore`, `flag` or `drop`).
+pub fn get_validation_rules() -> Result<ValidationRules> {
+  dotenv().ok();
+  let mut rules = ValidationRules {
+    outliers: env_setting("VALIDATION_OUTLIERS", str::parse)?,
+    ..ValidationRules::default()
+  };
+
+  if let Some(action) = env_setting("VALIDATION_OUTLIER_ACTION", str::parse)? {
+    rules.outlier_action = action;
+  }
+  if let Some(action) = env_setting("VALIDATION_DUPLICATES", str::parse)? {
+    rules.duplicates = action;
+  }
+  Ok(rules)
+}
+
+/// Loads the dataset configured by `DATASET_PATH` in the precision `T`,
+/// keeping the rows that pass validation, as the trainer does.
+pub fn load_training_dataset<T: Float>() -> Result<Dataset<T>> {
+  Ok(Dataset::load()?.clean(&get_validation_rules()?).0)
+}
+
+/// Fingerprints the dataset configured by `DATASET_PATH`: loaded and
+/// validated as for training, or, when `streamed`, read as
+/// `StreamingTrainer` reads it.
+pub fn dataset_fingerprint<T: Float>(streamed: bool) -> Result<DatasetFingerprint> {
+  if !streamed {
+    return Ok(load_training_dataset::<T>()?.fingerprint());
+  }
+  StreamingTrainer::<T>::from_env(FINGERPRINT_CHUNK_SIZE)?.fingerprint()
+}
+
+/// Compares the fingerprint a model was saved with to the dataset
+/// configured by `DATASET_PATH`, read the same way the model's was.
+pub fn check_dataset_fingerprint<T: Float>(trained: Option<&DatasetFingerprint>) -> Result<DatasetMatch> {
+  let streamed = trained.is_some_and(|trained| trained.streamed);
+  Ok(DatasetMatch::of(trained, dataset_fingerprint::<T>(streamed)?))
+}
+
+/// Reads and parses an optional environment variable.
+fn env_setting<V>(variable: &str, parse: impl Fn(&str) -> std::result::Result<V, String>) -> Result<Option<V>> {
+  match env::var(variable) {
+    Ok(value) => parse(&value).map(Some).map_err(|message| LinearRegressionError::InvalidConfig {
+      variable: variable.to_string(),
+      message,
+    }),
+    Err(_) => Ok(None),
+  }
+}
+
+/// Parses a single character, accepting `tab` and `\t` for a tab.
+fn parse_char(value: &str) -> std::result::Result<char, String> {
+  match value {
+    "tab" | "\\t" => return Ok('\t'),
+    "space" => return Ok(' '),
+    _ => {}
+  }
+  let mut chars = value.chars();
+  match (chars.next(), chars.next()) {
+    (Some(c), None) => Ok(c),
+    _ => Err(format!("expected a single character, found '{}'", value)),
+  }
+}
+
+/// Parses a single ASCII character, as the CSV reader requires.
+fn parse_byte(value: &str) -> std::result::Result<u8, String> {
+  let c = parse_char(value)?;
+  u8::try_from(c)
+    .ok()
+    .filter(u8::is_ascii)
+    .ok_or_else(|| format!("'{}' is not an ASCII character", c))
+}
+
+/// Retrieves the model file path from the `.env` file.
+pub fn get_model_path() -> Result<String> {
+  dotenv().ok();
+
+  Ok(env::var("MODEL_PATH").unwrap_or("./data/model.json".to_string()))
+}
+
+/// Retrieves the model registry directory from the `.env` file.
+pub fn get_registry_path() -> Result<String> {
+  dotenv().ok();
+
+  Ok(env::var("REGISTRY_PATH").unwrap_or("./data/models".to_string()))
+}
+
+/// Retrieves the registered model to use instead of `MODEL_PATH`, by tag
+/// or id, from the `.env` file.
+pub fn get_registry_model() -> Result<Option<String>> {
+  dotenv().ok();
+
+  Ok(env::var("REGISTRY_MODEL").ok().filter(|reference| !reference.trim().is_empty()))
+}
+
 /// Retrieves the theta file path from the `.env` file.
-/// Creates it if it doesn't exist.
-pub fn get_theta_path() -> io::Result<String> {
+pub fn get_theta_path() -> Result<String> {
+  dotenv().ok();
+
+  Ok(env::var("THETA_PATH").unwrap_or("./data/theta.txt".to_string()))
+}
+
+/// Retrieves the training precision (`f32` or `f64`) from the `.env` file.
+/// Defaults to `f64`.
+pub fn get_precision() -> Result<Precision> {
   dotenv().ok();
-  let path = env::var("THETA_PATH").unwrap_or("./data/theta.txt".to_string());
 
-  if !Path::new(&path).exists() {
-    File::create(&path)?;
+  match env::var("PRECISION") {
+    Ok(value) => value.parse().map_err(|message| LinearRegressionError::InvalidConfig {
+      variable: "PRECISION".to_string(),
+      message,
+    }),
+    Err(_) => Ok(Precision::default()),
   }
+}
+
+/// Retrieves the bounds trained models keep their predictions within from
+/// the `.env` file: `PREDICTION_CLAMP` is `none`, `non-negative`, or
+/// `floor=<price>` and `ceiling=<price>` separated by a comma. Defaults to `none`.
+pub fn get_prediction_clamp() -> Result<ClampPolicy> {
+  dotenv().ok();
 
-  Ok(path)
+  match env::var("PREDICTION_CLAMP") {
+    Ok(value) => value.parse().map_err(|message| LinearRegressionError::InvalidConfig {
+      variable: "PREDICTION_CLAMP".to_string(),
+      message,
+    }),
+    Err(_) => Ok(ClampPolicy::default()),
+  }
 }
 
-/// Saves the theta parameters to the file
-pub fn save_params(theta0: f64, theta1: f64) -> io::Result<()> {
-  let path = get_theta_path()?;
-  let mut file = File::create(path)?;
-  writeln!(file, "{},{}", theta0, theta1)?;
-  Ok(())
+/// Layouts a parameter file can have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterFileFormat {
+  /// `theta0,theta1[,precision]`, optionally followed by the normalization
+  /// factors and the imputation, as written by older versions. Only read,
+  /// to be migrated.
+  Legacy,
+  /// A versioned, self-contained `ModelFile`
+  Model,
 }
 
-/// Loads the theta files if they exist
-pub fn load_params() -> io::Result<(f64, f64)> {
-  let path = get_theta_path()?;
-  let file = File::open(path)?;
-  let line = io::BufReader::new(file).lines().next().unwrap_or(Ok("0.0,0.0".to_string()))?;
-  let params: Vec<f64> = line.split(',').filter_map(|v| v.trim().parse::<f64>().ok()).collect();
-  if params.len() != 2 {
-    return Err(io::Error::new(io::ErrorKind::InvalidData, "Theta file corrupt or missing values"));
+/// A model read by `read_model`, and what could not be recovered from its file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadedModel<T: Float = f64> {
+  pub model: ModelFile<T>,
+  pub format: ParameterFileFormat,
+  /// Problems worth telling the user about, e.g. a legacy file read
+  /// without its dataset
+  pub warnings: Vec<String>,
+}
+
+/// Tells the two parameter file layouts apart. Fails with `ModelNotFound`
+/// when the file is missing or empty.
+pub fn detect_parameter_format(path: &str) -> Result<ParameterFileFormat> {
+  match read_parameter_file(path)?.trim_start().chars().next() {
+    None => Err(LinearRegressionError::ModelNotFound { path: path.to_string() }),
+    Some('{') => Ok(ParameterFileFormat::Model),
+    Some(_) => Ok(ParameterFileFormat::Legacy),
   }
-  Ok((params[0], params[1]))
+}
+
+/// Reads the precision of the model at `path`, in either layout.
+pub fn read_model_precision(path: &str) -> Result<Precision> {
+  match detect_parameter_format(path)? {
+    ParameterFileFormat::Model => model_precision(path),
+    ParameterFileFormat::Legacy => parse_params_precision(path, &first_line(&read_theta_lines(path)?)),
+  }
+}
+
+/// Reads the model at `path`, in either layout.
+///
+/// Legacy files are turned into a full model. `dataset` should be the one
+/// the parameters were trained on: it supplies the normalization when the
+/// file has none, and the fingerprint and metrics, which legacy files never
+/// hold. Without it, what is missing is listed in `warnings`, and a fitted
+/// file without normalization factors fails with `CorruptParameterFile`:
+/// older versions always trained on min-max scaled data, so its parameters
+/// cannot be used without knowing that scale.
+pub fn read_model<T: Float>(path: &str, dataset: Option<&Dataset<T>>) -> Result<LoadedModel<T>> {
+  let format = detect_parameter_format(path)?;
+  if format == ParameterFileFormat::Model {
+    return Ok(LoadedModel { model: ModelFile::load(path)?, format, warnings: Vec::new() });
+  }
+
+  let lines = read_theta_lines(path)?;
+  let (theta0, theta1) = parse_params::<T>(path, &first_line(&lines))?;
+  let normalization: Option<NormalizationFactors<T>> = parse_json_line(path, &lines, 1, "normalization factors")?;
+  let imputation: Option<Imputer> = parse_json_line(path, &lines, 2, "imputation")?;
+  let mut warnings = Vec::new();
+
+  let mut model = LinearRegression::<T>::new(None);
+  match (normalization, dataset) {
+    (Some(factors), _) => model.set_normalization(factors),
+    (None, Some(dataset)) => model.set_dataset(dataset)?,
+    // Unfitted parameters predict nothing, whatever their scale
+    (None, None) if theta0 == T::zero() && theta1 == T::zero() => {}
+    (None, None) => {
+      return Err(LinearRegressionError::CorruptParameterFile {
+        path: path.to_string(),
+        reason: "it holds no normalization factors; set DATASET_PATH to the dataset it was trained on, \
+                 or run the trainer again"
+          .to_string(),
+      });
+    }
+  }
+  model.set_params(theta0, theta1);
+  // Older versions wrote all-zero parameters before any training
+  model.set_trained(theta0 != T::zero() || theta1 != T::zero());
+  model.set_imputation(imputation.or_else(|| dataset.and_then(|dataset| dataset.imputation().copied())));
+  model.set_dataset_fingerprint(dataset.map(Dataset::fingerprint));
+  if dataset.is_none() {
+    warnings.push(format!("'{}' was read without its dataset, so the model has no fingerprint or metrics", path));
+  }
+
+  let mut model_file = ModelFile::from_model(&model);
+  model_file.metrics = dataset.and_then(|dataset| Metrics::evaluate(&model, dataset.records()));
+  if let Ok(modified) = fs::metadata(path).and_then(|metadata| metadata.modified()) {
+    model_file.created_at = modified.into();
+  }
+  Ok(LoadedModel { model: model_file, format, warnings })
+}
+
+/// Rewrites the model at `from` as a self-contained model file at `to`,
+/// reconstructing it from `dataset` as `read_model` does.
+pub fn migrate_model<T: Float>(from: &str, to: &str, dataset: Option<&Dataset<T>>) -> Result<LoadedModel<T>> {
+  let mut loaded = read_model(from, dataset)?;
+  loaded.model.save(to)?;
+  Ok(loaded)
+}
+
+/// Reads a parameter file, reporting a missing one as `ModelNotFound`.
+fn read_parameter_file(path: &str) -> Result<String> {
+  read_locked(path).map_err(|err| match err.kind() {
+    io::ErrorKind::NotFound => LinearRegressionError::ModelNotFound { path: path.to_string() },
+    _ => err.into(),
+  })
+}
+
+/// Reads the lines of a legacy parameter file.
+fn read_theta_lines(path: &str) -> Result<Vec<String>> {
+  Ok(read_parameter_file(path)?.lines().map(str::to_string).collect())
+}
+
+/// The parameters line, which empty files are read as holding zeros
+fn first_line(lines: &[String]) -> String {
+  lines.first().cloned().unwrap_or("0.0,0.0".to_string())
+}
+
+/// Parses `theta0,theta1[,precision]`.
+fn parse_params<T: Float>(path: &str, line: &str) -> Result<(T, T)> {
+  let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+  let corrupt = |reason: String| LinearRegressionError::CorruptParameterFile { path: path.to_string(), reason };
+
+  if !(2..=3).contains(&fields.len()) {
+    return Err(corrupt(format!("expected 'theta0,theta1[,precision]', found '{}'", line)));
+  }
+  if let Some(&tag) = fields.get(2) {
+    let precision: Precision = tag.parse().map_err(corrupt)?;
+    if precision != T::PRECISION {
+      return Err(LinearRegressionError::VersionMismatch {
+        path: path.to_string(),
+        expected: T::PRECISION.to_string(),
+        found: precision.to_string(),
+      });
+    }
+  }
+
+  let parse = |value: &str| value.parse::<T>().map_err(|_| corrupt(format!("'{}' is not a number", value)));
+  Ok((parse(fields[0])?, parse(fields[1])?))
+}
+
+fn parse_params_precision(path: &str, line: &str) -> Result<Precision> {
+  match line.split(',').nth(2) {
+    Some(tag) => tag.parse().map_err(|reason| LinearRegressionError::CorruptParameterFile { path: path.to_string(), reason }),
+    None => Ok(Precision::F64),
+  }
+}
+
+/// Parses line `index` of a legacy parameter file as the JSON of a `what`.
+fn parse_json_line<V: serde::de::DeserializeOwned>(path: &str, lines: &[String], index: usize, what: &str) -> Result<Option<V>> {
+  let line = match lines.get(index) {
+    Some(line) if !line.trim().is_empty() => line,
+    _ => return Ok(None),
+  };
+  serde_json::from_str(line).map(Some).map_err(|err| LinearRegressionError::CorruptParameterFile {
+    path: path.to_string(),
+    reason: format!("invalid {} ({})", what, err),
+  })
 }
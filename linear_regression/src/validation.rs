@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use crate::dataset::Dataset;
+use crate::describe::Summary;
+use crate::float::Float;
+
+/// What cleaning does with a row a rule matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+  /// Don't check the rule
+  Ignore,
+  /// Report the row but keep it
+  Flag,
+  /// Report the row and remove it
+  Drop,
+}
+
+impl FromStr for Action {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_ascii_lowercase().as_str() {
+      "ignore" => Ok(Action::Ignore),
+      "flag" => Ok(Action::Flag),
+      "drop" => Ok(Action::Drop),
+      other => Err(format!("Unknown action '{}', expected ignore, flag or drop", other)),
+    }
+  }
+}
+
+/// How outliers are told apart from the rest of a column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlierMethod {
+  /// Outside `[q1 − k·IQR, q3 + k·IQR]`
+  Iqr { k: f64 },
+  /// More than `threshold` standard deviations from the mean
+  ZScore { threshold: f64 },
+}
+
+impl FromStr for OutlierMethod {
+  type Err = String;
+
+  /// `iqr`, `iqr:<k>`, `zscore` or `zscore:<threshold>`
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (method, parameter) = match s.trim().split_once(':') {
+      Some((method, parameter)) => (method, Some(parameter)),
+      None => (s.trim(), None),
+    };
+    let parameter = |default: f64| match parameter {
+      Some(value) => value.trim().parse().map_err(|_| format!("Invalid outlier parameter '{}'", value)),
+      None => Ok(default),
+    };
+
+    match method.to_ascii_lowercase().as_str() {
+      "iqr" => Ok(OutlierMethod::Iqr { k: parameter(1.5)? }),
+      "zscore" => Ok(OutlierMethod::ZScore { threshold: parameter(3.0)? }),
+      other => Err(format!("Unknown outlier method '{}', expected iqr or zscore", other)),
+    }
+  }
+}
+
+/// Checks run before training, and what to do with the rows they match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValidationRules {
+  /// NaN or infinite values
+  pub non_finite: Action,
+  /// Negative feature values (mileage)
+  pub negative_feature: Action,
+  /// Zero or negative target values (price)
+  pub non_positive_target: Action,
+  /// Rows identical to an earlier row
+  pub duplicates: Action,
+  pub outliers: Option<OutlierMethod>,
+  pub outlier_action: Action,
+}
+
+impl Default for ValidationRules {
+  fn default() -> Self {
+    Self {
+      non_finite: Action::Drop,
+      negative_feature: Action::Drop,
+      non_positive_target: Action::Drop,
+      duplicates: Action::Flag,
+      outliers: None,
+      outlier_action: Action::Flag,
+    }
+  }
+}
+
+/// Which column of a row an issue was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+  Feature,
+  Target,
+}
+
+/// A problem with one row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Issue {
+  NonFinite(Side),
+  NegativeFeature(f64),
+  NonPositiveTarget(f64),
+  /// Same values as the row at this index
+  Duplicate(usize),
+  Outlier { side: Side, value: f64, method: OutlierMethod },
+}
+
+/// A row matched by a rule and the action taken.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+  /// Index of the row in the dataset that was validated
+  pub row: usize,
+  /// Line of the source file, when known
+  pub line: Option<usize>,
+  pub issue: Issue,
+  pub action: Action,
+}
+
+/// Every finding of a validation pass, plus dataset-wide problems.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationReport {
+  pub feature_name: String,
+  pub target_name: String,
+  pub findings: Vec<Finding>,
+  /// Names of columns whose remaining values are all equal
+  pub constant_columns: Vec<String>,
+  pub rows_kept: usize,
+  pub rows_dropped: usize,
+}
+
+impl ValidationReport {
+  /// True when no rule matched and no column is constant
+  pub fn is_clean(&self) -> bool {
+    self.findings.is_empty() && self.constant_columns.is_empty()
+  }
+
+  fn describe_issue(&self, issue: &Issue) -> String {
+    let name = |side: &Side| match side {
+      Side::Feature => &self.feature_name,
+      Side::Target => &self.target_name,
+    };
+    match issue {
+      Issue::NonFinite(side) => format!("{} is NaN or infinite", name(side)),
+      Issue::NegativeFeature(value) => format!("{} is negative ({})", self.feature_name, value),
+      Issue::NonPositiveTarget(value) => format!("{} is not positive ({})", self.target_name, value),
+      Issue::Duplicate(first) => format!("duplicate of row {}", first),
+      Issue::Outlier { side, value, method } => match method {
+        OutlierMethod::Iqr { k } => format!("{} {} is an outlier (IQR, k = {})", name(side), value, k),
+        OutlierMethod::ZScore { threshold } => format!("{} {} is an outlier (z-score > {})", name(side), value, threshold),
+      },
+    }
+  }
+}
+
+impl fmt::Display for ValidationReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Validation: {} rows kept, {} dropped", self.rows_kept, self.rows_dropped)?;
+    for finding in &self.findings {
+      let action = match finding.action {
+        Action::Drop => "dropped",
+        _ => "flagged",
+      };
+      match finding.line {
+        Some(line) => write!(f, "\n  row {} (line {}): ", finding.row, line)?,
+        None => write!(f, "\n  row {}: ", finding.row)?,
+      }
+      write!(f, "{}, {}", self.describe_issue(&finding.issue), action)?;
+    }
+    for column in &self.constant_columns {
+      write!(f, "\n  {} is constant", column)?;
+    }
+    Ok(())
+  }
+}
+
+impl ValidationRules {
+  /// Checks `dataset` against the rules, returning the rows that were not
+  /// dropped and a report of every affected row.
+  ///
+  /// Rules run in order: non-finite values, ranges, duplicates, then
+  /// outliers, which are measured on the rows the earlier rules kept.
+  pub fn clean<T: Float>(&self, dataset: &Dataset<T>) -> (Dataset<T>, ValidationReport) {
+    let records = dataset.records();
+    let mut keep = vec![true; records.len()];
+    let mut report = ValidationReport {
+      feature_name: dataset.feature().to_string(),
+      target_name: dataset.target().to_string(),
+      ..ValidationReport::default()
+    };
+    let mut record = |row: usize, issue: Issue, action: Action, keep: &mut Vec<bool>| {
+      if action == Action::Drop {
+        keep[row] = false;
+      }
+      report.findings.push(Finding { row, line: dataset.line(row), issue, action });
+    };
+
+    let mut first_seen = HashMap::new();
+    for (row, &(x, y)) in records.iter().enumerate() {
+      let (x, y) = (x.as_f64(), y.as_f64());
+      if self.non_finite != Action::Ignore && !(x.is_finite() && y.is_finite()) {
+        let side = if x.is_finite() { Side::Target } else { Side::Feature };
+        record(row, Issue::NonFinite(side), self.non_finite, &mut keep);
+        if self.non_finite == Action::Drop {
+          continue;
+        }
+      }
+      if self.negative_feature != Action::Ignore && x < 0.0 {
+        record(row, Issue::NegativeFeature(x), self.negative_feature, &mut keep);
+      }
+      if self.non_positive_target != Action::Ignore && y <= 0.0 {
+        record(row, Issue::NonPositiveTarget(y), self.non_positive_target, &mut keep);
+      }
+      if self.duplicates != Action::Ignore && keep[row] {
+        match first_seen.get(&(x.to_bits(), y.to_bits())) {
+          Some(&first) => record(row, Issue::Duplicate(first), self.duplicates, &mut keep),
+          None => {
+            first_seen.insert((x.to_bits(), y.to_bits()), row);
+          }
+        }
+      }
+    }
+
+    if let (Some(method), true) = (self.outliers, self.outlier_action != Action::Ignore) {
+      let kept: Vec<usize> = (0..records.len()).filter(|&row| keep[row]).collect();
+      for side in [Side::Feature, Side::Target] {
+        let value = |row: usize| match side {
+          Side::Feature => records[row].0.as_f64(),
+          Side::Target => records[row].1.as_f64(),
+        };
+        let values: Vec<f64> = kept.iter().map(|&row| value(row)).collect();
+        let summary = Summary::of(&values);
+        for &row in &kept {
+          let v = value(row);
+          let outlier = match method {
+            OutlierMethod::Iqr { k } => v < summary.q1 - k * summary.iqr() || v > summary.q3 + k * summary.iqr(),
+            OutlierMethod::ZScore { threshold } => summary.std > 0.0 && ((v - summary.mean) / summary.std).abs() > threshold,
+          };
+          if outlier {
+            record(row, Issue::Outlier { side, value: v, method }, self.outlier_action, &mut keep);
+          }
+        }
+      }
+    }
+
+    let cleaned = dataset.filter_rows(|row| keep[row]);
+    let constant = |values: Vec<T>| values.windows(2).all(|pair| pair[0] == pair[1]);
+    let (x, y): (Vec<T>, Vec<T>) = cleaned.records().iter().copied().unzip();
+    if !cleaned.is_empty() && constant(x) {
+      report.constant_columns.push(report.feature_name.clone());
+    }
+    if !cleaned.is_empty() && constant(y) {
+      report.constant_columns.push(report.target_name.clone());
+    }
+
+    report.findings.sort_by_key(|finding| finding.row);
+    report.rows_kept = cleaned.len();
+    report.rows_dropped = records.len() - cleaned.len();
+    (cleaned, report)
+  }
+}
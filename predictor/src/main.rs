@@ -1,22 +1,92 @@
 use linear_regression::linear_regression::LinearRegression;
-use linear_regression::utils::load_params;
+use linear_regression::utils::{
+  check_dataset_fingerprint, detect_parameter_format, get_model_path, get_registry_model, get_registry_path, get_theta_path,
+  load_training_dataset, read_model, read_model_precision, ParameterFileFormat,
+};
+use linear_regression::registry::Registry;
+use linear_regression::error::LinearRegressionError;
+use linear_regression::float::{Float, Precision};
+use linear_regression::imputation::MissingValues;
 use inquire::Text;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use std::process::ExitCode;
 use std::thread::sleep;
 use std::time::Duration;
 
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-  let (theta0, theta1) = load_params()?;
+fn main() -> ExitCode {
+  match run() {
+    Ok(()) => ExitCode::SUCCESS,
+    Err(err) => report(err),
+  }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+  let args: Vec<String> = env::args().skip(1).collect();
+  // Checking the model against the dataset reads it whole; allow skipping it
+  let check_dataset = !args.iter().any(|arg| arg == "--no-check-dataset");
+  // A registered model, by tag or id, takes precedence over MODEL_PATH
+  let reference = args.iter().find_map(|arg| arg.strip_prefix("--model=").map(str::to_string));
+  if let Some(reference) = reference.map_or_else(get_registry_model, |reference| Ok(Some(reference)))? {
+    let registry = Registry::open(&get_registry_path()?)?;
+    let entry = registry.resolve(&reference)?;
+    println!("Using model {} from {}", entry.id, registry.root());
+    return match entry.precision {
+      Precision::F32 => predict(registry.load::<f32>(&entry.id)?.to_model(), check_dataset),
+      Precision::F64 => predict(registry.load::<f64>(&entry.id)?.to_model(), check_dataset),
+    };
+  }
 
-  let mut model = LinearRegression::new(None)?;
-  model.set_params(theta0, theta1);
+  let model_path = get_model_path()?;
+  let theta_path = get_theta_path()?;
+  // Fall back on the parameter file written by older versions
+  let path = if !Path::new(&model_path).exists() && Path::new(&theta_path).exists() { theta_path } else { model_path };
+  match read_model_precision(&path)? {
+    Precision::F32 => predict(load_model::<f32>(&path)?, check_dataset),
+    Precision::F64 => predict(load_model::<f64>(&path)?, check_dataset),
+  }
+}
 
+/// Loads the model at `path`. Legacy parameter files are reconstructed with
+/// the dataset when it can be loaded, with a warning for anything missing.
+fn load_model<T: Float>(path: &str) -> Result<LinearRegression<T>, Box<dyn Error>> {
+  let dataset = match detect_parameter_format(path)? {
+    ParameterFileFormat::Legacy => load_training_dataset().ok(),
+    ParameterFileFormat::Model => None,
+  };
+  let loaded = read_model::<T>(path, dataset.as_ref())?;
+  if loaded.format == ParameterFileFormat::Legacy {
+    for warning in &loaded.warnings {
+      eprintln!("Warning: {}", warning);
+    }
+    eprintln!("Run `make migrate` to convert '{}' to a self-contained model file.", path);
+  }
+  Ok(loaded.model.to_model())
+}
+
+/// Asks for a mileage and prints the price `model` predicts for it, after
+/// checking `model` against the dataset unless `check_dataset` is unset.
+fn predict<T: Float>(model: LinearRegression<T>, check_dataset: bool) -> Result<(), Box<dyn Error>> {
+  if check_dataset {
+    warn_if_stale(&model);
+  }
   let mileage_input = Text::new("Enter mileage (in kilometers):")
     .with_placeholder("e.g., 420000")
     .prompt()?;
 
-  let mileage: f64 = mileage_input.trim().parse()?;
+  let mileage: T = match model.get_imputation() {
+    // Replay the imputation the training data went through
+    Some(imputation) if mileage_input.trim().is_empty() => {
+      let mileage = imputation.feature(None);
+      let statistic = if imputation.strategy == MissingValues::Median { "median" } else { "mean" };
+      println!("No mileage given, using the {} mileage of the training data: {:.2} km", statistic, mileage);
+      mileage
+    }
+    _ => mileage_input.trim().parse()?,
+  };
 
   let pb = ProgressBar::new(42);
   pb.set_style(
@@ -31,12 +101,40 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     sleep(Duration::from_millis(10));
   }
 
-  let estimated_price = model.predict(mileage);
+  let prediction = model.predict(mileage)?;
 
   println!(
     "\n🚗 The estimated price for a car with mileage {:.2} km is: {:.2} 💸",
-    mileage, estimated_price
+    mileage, prediction.value
   );
+  if let Some(extrapolation) = prediction.extrapolation {
+    eprintln!("Warning: {:.2} km is {}, the estimate is an extrapolation.", mileage, extrapolation);
+  }
+  if prediction.is_clamped() {
+    println!("The fitted line gives {:.2}, clamped to {}.", prediction.unclamped, model.get_clamp());
+  }
 
   Ok(())
-}
\ No newline at end of file
+}
+
+/// Warns when `model` was trained on other data than the dataset at
+/// `DATASET_PATH`, or when the dataset cannot be read to check it.
+fn warn_if_stale<T: Float>(model: &LinearRegression<T>) {
+  match check_dataset_fingerprint::<T>(model.get_dataset_fingerprint()) {
+    Ok(check) => {
+      if let Some(warning) = check.warning() {
+        eprintln!("Warning: {}", warning);
+      }
+    }
+    Err(err) => eprintln!("Warning: the model cannot be checked against the dataset: {}", err),
+  }
+}
+
+/// Prints `err`, with a hint on how to fix it when it comes from the library.
+fn report(err: Box<dyn Error>) -> ExitCode {
+  eprintln!("Error: {}", err);
+  if let Some(err) = err.downcast_ref::<LinearRegressionError>() {
+    eprintln!("Hint: {}", err.hint());
+  }
+  ExitCode::FAILURE
+}
@@ -1,19 +1,75 @@
+use std::collections::HashMap;
 use std::env;
+use std::process::ExitCode;
 use std::thread::sleep;
 use std::time::Duration;
 use linear_regression::linear_regression::LinearRegression;
-use linear_regression::utils::save_params;
+use linear_regression::streaming::StreamingTrainer;
+use linear_regression::utils::{
+    dataset_fingerprint, detect_parameter_format, get_model_path, get_precision, get_prediction_clamp, get_registry_path, get_theta_path, get_validation_rules,
+    migrate_model, read_model_precision, ParameterFileFormat,
+};
+use linear_regression::model_file::{model_precision, ModelFile};
+use linear_regression::export::{export, ExportOptions, Language};
+use linear_regression::pmml::{load_pmml, to_pmml};
+use linear_regression::registry::{Registry, PRODUCTION_TAG};
+use linear_regression::dataset::{Dataset, DatasetMatch};
+use linear_regression::drift::{read_inputs, DriftMonitor, DriftThresholds};
+use linear_regression::optimizer::StoppingCriteria;
+use linear_regression::error::LinearRegressionError;
+use linear_regression::float::{Float, Precision};
+use linear_regression::observer::{LogObserver, TrainingControl, TrainingObserver, TrainingStep};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::error::Error;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let iterations: usize = env::args()
-        .nth(1)
-        .and_then(|arg| arg.parse().ok())
+/// Rows read at a time in `--stream` mode.
+const STREAM_CHUNK_SIZE: usize = 65_536;
+
+/// Steps between log lines in `--verbose` mode.
+const LOG_EVERY: usize = 100;
+
+/// Arguments of the `registry` subcommand.
+const REGISTRY_USAGE: &str =
+    "list | inspect <model> | tag <tag> <model> | diff <model> <model> | promote <model> | rollback [tag]";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => report(err),
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("migrate") => return migrate(&args[1..]),
+        Some("registry") => return registry(&args[1..]),
+        Some("export") => return export_model(&args[1..]),
+        Some("import") => return import_model(&args[1..]),
+        Some("drift") => return drift(&args[1..]),
+        _ => {}
+    }
+    let streaming = args.iter().any(|arg| arg == "--stream");
+    let verbose = args.iter().any(|arg| arg == "--verbose");
+    let history_path = args.iter().find_map(|arg| arg.strip_prefix("--history="));
+    let iterations: usize = args
+        .iter()
+        .find_map(|arg| arg.parse().ok())
         .unwrap_or(42);
 
-    let mut model = LinearRegression::new(Some(1.0))?;
+    match get_precision()? {
+        Precision::F32 => train::<f32>(iterations, streaming, verbose, history_path),
+        Precision::F64 => train::<f64>(iterations, streaming, verbose, history_path),
+    }
+}
 
+/// Trains a model in the precision `T` and saves its parameters.
+fn train<T: Float>(
+    iterations: usize,
+    streaming: bool,
+    verbose: bool,
+    history_path: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
     let pb = ProgressBar::new(iterations as u64);
     pb.set_style(
         ProgressStyle::default_bar()
@@ -22,19 +78,333 @@ fn main() -> Result<(), Box<dyn Error>> {
             .progress_chars("##-"),
     );
 
-    for i in 0..iterations {
-        model.train(1);
-        pb.set_position(i as u64);
-    }
+    let progress = |step: &TrainingStep<T>| {
+        pb.set_position(step.iteration as u64 + 1);
+        TrainingControl::Continue
+    };
+    let mut observer = (progress, verbose.then(|| LogObserver::new(LOG_EVERY)));
+
+    let mut model = if streaming {
+        train_streaming::<T>(iterations, &mut observer)?
+    } else {
+        train_in_memory::<T>(iterations, &mut observer)?
+    };
+    model.set_clamp(get_prediction_clamp()?);
     sleep(Duration::from_millis(42));
     pb.set_position(iterations as u64);
 
+    let steps = model.get_history().len();
+    if steps < iterations {
+        println!("Converged after {} iterations.", steps);
+    }
+
+    let model_path = get_model_path()?;
+    report_replaced_model(&model_path, &model);
+    let mut model_file = ModelFile::from_model(&model);
+    model_file.save(&model_path)?;
+    let registry = Registry::open(&get_registry_path()?)?;
+    let entry = registry.register(&model_file)?;
     let (theta0, theta1) = model.get_params();
-    save_params(theta0, theta1)?;
 
     println!(
-        "Training complete! Parameters saved:\nθ₀ = {:.4}\nθ₁ = {:.4}",
-        theta0, theta1
+        "Training complete ({})! Model saved to {}:\nθ₀ = {:.4}\nθ₁ = {:.4}",
+        T::PRECISION, model_path, theta0, theta1
     );
+    if !model.get_clamp().is_none() {
+        println!("Predictions are clamped: {}", model.get_clamp());
+    }
+    println!("Registered as {} in {}", entry.id, registry.root());
+
+    if let Some(path) = history_path {
+        model.get_history().export(path)?;
+        println!("Training history ({} steps) written to {}", model.get_history().len(), path);
+    }
     Ok(())
 }
+
+/// Tells when the model saved at `path`, about to be replaced by `model`,
+/// was trained on other data.
+fn report_replaced_model<T: Float>(path: &str, model: &LinearRegression<T>) {
+    let (Ok(previous), Some(current)) = (ModelFile::<T>::load(path), model.get_dataset_fingerprint()) else {
+        return;
+    };
+    let Some(trained) = previous.dataset else {
+        return;
+    };
+    // Streamed and loaded fingerprints hash different rows, so read the
+    // dataset again the way the previous model did
+    let current = if trained.streamed == current.streamed {
+        current.clone()
+    } else {
+        match dataset_fingerprint::<T>(trained.streamed) {
+            Ok(fingerprint) => fingerprint,
+            Err(_) => return,
+        }
+    };
+    if let DatasetMatch::Changed { trained, .. } = DatasetMatch::of(Some(&trained), current) {
+        println!("The dataset changed since the model at {} was trained on {}.", path, trained);
+    }
+}
+
+/// Loads the whole dataset and trains on it.
+fn train_in_memory<T: Float>(
+    iterations: usize,
+    observer: &mut impl TrainingObserver<T>,
+) -> Result<LinearRegression<T>, Box<dyn Error>> {
+    let mut model = LinearRegression::<T>::builder()
+        .learning_rate(T::one())
+        .stopping(StoppingCriteria { max_iterations: iterations, ..Default::default() })
+        .build();
+    let dataset = Dataset::load()?;
+    println!("Dataset: {} ({} rows)", dataset.source(), dataset.len());
+    if !dataset.load_report().is_complete() {
+        println!("{}", dataset.load_report());
+    }
+    let (dataset, validation) = dataset.clean(&get_validation_rules()?);
+    if !validation.is_clean() {
+        println!("{}", validation);
+    }
+    println!("{}", dataset.describe());
+    model.fit_with(&dataset, observer)?;
+    Ok(model)
+}
+
+/// Re-reads the dataset file in chunks every epoch, keeping memory bounded.
+fn train_streaming<T: Float>(
+    iterations: usize,
+    observer: &mut impl TrainingObserver<T>,
+) -> Result<LinearRegression<T>, Box<dyn Error>> {
+    let mut trainer = StreamingTrainer::<T>::from_env(STREAM_CHUNK_SIZE)?;
+    let mut model = LinearRegression::<T>::new(Some(T::one()));
+    trainer.init(&mut model)?;
+    println!("Dataset: {} ({} rows, streamed)", trainer.source(), trainer.rows());
+    if !trainer.load_report().is_complete() {
+        println!("{}", trainer.load_report());
+    }
+    trainer.train_with(&mut model, iterations, observer)?;
+    Ok(model)
+}
+
+/// Rewrites a legacy parameter file (`THETA_PATH` by default) as a
+/// self-contained model file (`MODEL_PATH` by default).
+fn migrate(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let from = match args.first() {
+        Some(path) => path.clone(),
+        None => get_theta_path()?,
+    };
+    let to = match args.get(1) {
+        Some(path) => path.clone(),
+        None => get_model_path()?,
+    };
+
+    if detect_parameter_format(&from)? == ParameterFileFormat::Model {
+        println!("'{}' is already a model file.", from);
+        return Ok(());
+    }
+    match read_model_precision(&from)? {
+        Precision::F32 => migrate_in::<f32>(&from, &to),
+        Precision::F64 => migrate_in::<f64>(&from, &to),
+    }
+}
+
+/// Migrates a legacy file holding parameters in the precision `T`,
+/// reconstructing the model with the dataset when it can be loaded.
+fn migrate_in<T: Float>(from: &str, to: &str) -> Result<(), Box<dyn Error>> {
+    let dataset = match Dataset::<T>::load() {
+        Ok(dataset) => Some(dataset.clean(&get_validation_rules()?).0),
+        Err(err) => {
+            eprintln!("Warning: cannot load the dataset ({}), migrating without it", err);
+            None
+        }
+    };
+
+    let migrated = migrate_model(from, to, dataset.as_ref())?;
+    for warning in &migrated.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    println!("Migrated '{}' to '{}'.", from, to);
+    Ok(())
+}
+
+/// Lists, inspects, tags, compares, promotes and rolls back the models
+/// in the registry (`REGISTRY_PATH`).
+fn registry(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let registry = Registry::open(&get_registry_path()?)?;
+    let arg = |index: usize, name: &str| {
+        args.get(index)
+            .map(String::as_str)
+            .ok_or_else(|| format!("Missing {}, usage: trainer registry {}", name, REGISTRY_USAGE))
+    };
+
+    match args.first().map(String::as_str).unwrap_or("list") {
+        "list" => {
+            let entries = registry.entries()?;
+            if entries.is_empty() {
+                println!("No model registered in {} yet, run the trainer.", registry.root());
+            }
+            // Fingerprints depend on the precision the dataset is read in,
+            // and on whether it was streamed
+            let current = |precision, streamed| match precision {
+                Precision::F32 => dataset_fingerprint::<f32>(streamed).ok(),
+                Precision::F64 => dataset_fingerprint::<f64>(streamed).ok(),
+            };
+            let mut fingerprints = HashMap::new();
+            for entry in entries {
+                let mut line = entry.to_string();
+                let tags = registry.tags_of(&entry.id)?;
+                if !tags.is_empty() {
+                    line.push_str(&format!("  [{}]", tags.join(", ")));
+                }
+                let streamed = entry.dataset.as_ref().is_some_and(|trained| trained.streamed);
+                let current = fingerprints
+                    .entry((entry.precision, streamed))
+                    .or_insert_with(|| current(entry.precision, streamed));
+                if let Some(current) = current {
+                    if matches!(DatasetMatch::of(entry.dataset.as_ref(), current.clone()), DatasetMatch::Changed { .. }) {
+                        line.push_str("  (stale: the dataset changed)");
+                    }
+                }
+                println!("{}", line);
+            }
+        }
+        "inspect" => {
+            let entry = registry.resolve(arg(1, "model")?)?;
+            println!("Model {} ({})", entry.id, registry.model_path(&entry));
+            let tags = registry.tags_of(&entry.id)?;
+            if !tags.is_empty() {
+                println!("Tags: {}", tags.join(", "));
+            }
+            println!("{:#}", registry.model_json(&entry)?);
+        }
+        "tag" => {
+            let (tag, reference) = (arg(1, "tag")?, arg(2, "model")?);
+            let entry = registry.tag(tag, reference)?;
+            println!("Tagged {} as '{}'.", entry.id, tag.trim());
+        }
+        "diff" => println!("{}", registry.diff(arg(1, "model")?, arg(2, "model")?)?),
+        "promote" => {
+            let entry = registry.promote(arg(1, "model")?)?;
+            println!("Promoted {} to '{}'.", entry.id, PRODUCTION_TAG);
+        }
+        "rollback" => {
+            let tag = args.get(1).map(String::as_str).unwrap_or(PRODUCTION_TAG);
+            let entry = registry.rollback(tag)?;
+            println!("Rolled '{}' back to {}.", tag, entry.id);
+        }
+        other => return Err(format!("Unknown registry command '{}', usage: trainer registry {}", other, REGISTRY_USAGE).into()),
+    }
+    Ok(())
+}
+
+/// Prints the model at `MODEL_PATH`, or the registered `--model=<tag or id>`,
+/// as source code in the language given as the first argument, or as PMML.
+fn export_model(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let format = args
+        .first()
+        .ok_or("Missing format, usage: trainer export <rust|c|python|javascript|sql|latex|pmml> [--model=<tag or id>]")?;
+
+    let code = match args.iter().find_map(|arg| arg.strip_prefix("--model=")) {
+        Some(reference) => {
+            let registry = Registry::open(&get_registry_path()?)?;
+            let entry = registry.resolve(reference)?;
+            match entry.precision {
+                Precision::F32 => render(&registry.load::<f32>(&entry.id)?.to_model(), format)?,
+                Precision::F64 => render(&registry.load::<f64>(&entry.id)?.to_model(), format)?,
+            }
+        }
+        None => {
+            let path = get_model_path()?;
+            match model_precision(&path)? {
+                Precision::F32 => render(&ModelFile::<f32>::load(&path)?.to_model(), format)?,
+                Precision::F64 => render(&ModelFile::<f64>::load(&path)?.to_model(), format)?,
+            }
+        }
+    };
+    print!("{}", code);
+    Ok(())
+}
+
+/// `model` as PMML, or as source code in the language `format` names.
+fn render<T: Float>(model: &LinearRegression<T>, format: &str) -> Result<String, Box<dyn Error>> {
+    let options = ExportOptions::default();
+    if format.eq_ignore_ascii_case("pmml") {
+        return Ok(to_pmml(model, &options.feature, &options.target)?);
+    }
+    Ok(export(model, format.parse::<Language>()?, &options)?)
+}
+
+/// Compares a file of prediction inputs with the mileages the model at
+/// `MODEL_PATH`, or the registered `--model=`, was trained on.
+fn drift(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let path = args
+        .first()
+        .ok_or("Missing file, usage: trainer drift <inputs.csv> [--model=<tag or id>] [--psi=<threshold>] [--alpha=<level>] [--min-count=<inputs>]")?;
+    let mut thresholds = DriftThresholds::default();
+    for arg in &args[1..] {
+        if let Some(psi) = arg.strip_prefix("--psi=") {
+            thresholds.psi = psi.parse().map_err(|_| format!("--psi '{}' is not a number", psi))?;
+        } else if let Some(alpha) = arg.strip_prefix("--alpha=") {
+            thresholds.ks_alpha = alpha.parse().map_err(|_| format!("--alpha '{}' is not a number", alpha))?;
+        } else if let Some(count) = arg.strip_prefix("--min-count=") {
+            thresholds.psi_min_count = count.parse().map_err(|_| format!("--min-count '{}' is not a count", count))?;
+        }
+    }
+
+    let distribution = match args.iter().find_map(|arg| arg.strip_prefix("--model=")) {
+        Some(reference) => {
+            let registry = Registry::open(&get_registry_path()?)?;
+            let entry = registry.resolve(reference)?;
+            match entry.precision {
+                Precision::F32 => registry.load::<f32>(&entry.id)?.distribution,
+                Precision::F64 => registry.load::<f64>(&entry.id)?.distribution,
+            }
+        }
+        None => {
+            let path = get_model_path()?;
+            match model_precision(&path)? {
+                Precision::F32 => ModelFile::<f32>::load(&path)?.distribution,
+                Precision::F64 => ModelFile::<f64>::load(&path)?.distribution,
+            }
+        }
+    };
+    let distribution = distribution.ok_or("The model has no training distribution to compare with, train it again")?;
+
+    let inputs = read_inputs::<f64>(path)?;
+    let report = DriftMonitor::new(distribution, thresholds)
+        .check(&inputs)
+        .ok_or_else(|| format!("'{}' holds no mileages", path))?;
+    println!("{}", report);
+    Ok(())
+}
+
+/// Reads a PMML regression model, saves it to `MODEL_PATH` in the
+/// configured precision and registers it.
+fn import_model(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let path = args.first().ok_or("Missing file, usage: trainer import <model.pmml>")?;
+    match get_precision()? {
+        Precision::F32 => import_in::<f32>(path),
+        Precision::F64 => import_in::<f64>(path),
+    }
+}
+
+fn import_in<T: Float>(path: &str) -> Result<(), Box<dyn Error>> {
+    let imported = load_pmml::<T>(path)?;
+    let model_path = get_model_path()?;
+    let mut model_file = ModelFile::from_model(&imported.model);
+    model_file.save(&model_path)?;
+    let entry = Registry::open(&get_registry_path()?)?.register(&model_file)?;
+    println!(
+        "Imported '{}' ({} from {}) to {}, registered as {}.",
+        path, imported.target, imported.feature, model_path, entry.id
+    );
+    Ok(())
+}
+
+/// Prints `err`, with a hint on how to fix it when it comes from the library.
+fn report(err: Box<dyn Error>) -> ExitCode {
+    eprintln!("Error: {}", err);
+    if let Some(err) = err.downcast_ref::<LinearRegressionError>() {
+        eprintln!("Hint: {}", err.hint());
+    }
+    ExitCode::FAILURE
+}